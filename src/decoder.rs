@@ -1,42 +1,222 @@
-use crate::field::{
-    FieldName, HeaderField, IndexedHeaderField, LiteralFieldForm, LiteralHeaderField,
-    RawHeaderField,
-};
+use crate::field::{FieldInfo, FieldName, HeaderField, LiteralFieldForm, RawHeaderField};
 use crate::io::SliceReader;
+use crate::literal::HpackString;
 use crate::signal::DynamicTableSizeUpdate;
-use crate::table::Table;
-use crate::Result;
-use trackable::error::Failed;
+use crate::table::{Index, Table};
+use crate::{ErrorKind, Result};
+use std::borrow::Cow;
+use std::cmp;
+
+/// Configuration of `Decoder` (mainly resource limits).
+///
+/// The default values impose no limits
+/// (`max_huffman_expansion_ratio` defaults to `1.6` that is the theoretical maximum).
+///
+/// # Examples
+///
+/// ```
+/// use hpack_codec::{Decoder, DecoderConfig};
+///
+/// let config = DecoderConfig {
+///     max_header_list_size: 16 * 1024,
+///     max_field_count: 128,
+///     max_string_length: 8 * 1024,
+///     ..DecoderConfig::default()
+/// };
+/// let decoder = Decoder::with_config(4096, config);
+/// assert_eq!(decoder.config().max_field_count, 128);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DecoderConfig {
+    /// The maximum value of integers (e.g., an index or the length of a string literal).
+    ///
+    /// If a header block contains an integer exceeding this, the decoding of the block will fail.
+    ///
+    /// See: [5.1.  Integer Representation](https://tools.ietf.org/html/rfc7541#section-5.1)
+    pub max_integer_value: u32,
+
+    /// The maximum size of a header list.
+    ///
+    /// The size of a header list is the sum of the sizes of the header fields in it,
+    /// and the size of a header field is the length of its name and value plus 32
+    /// (i.e., the same as the entry size of the field).
+    /// If the decoded header list of a block exceeds this,
+    /// an error that has the kind `ErrorKind::HeaderListTooLarge` will be returned.
+    ///
+    /// This corresponds to `SETTINGS_MAX_HEADER_LIST_SIZE` of HTTP/2.
+    ///
+    /// See: [6.5.2.  Defined SETTINGS Parameters](https://tools.ietf.org/html/rfc7540#section-6.5.2)
+    pub max_header_list_size: usize,
+
+    /// The maximum number of header fields in a header block.
+    pub max_field_count: usize,
+
+    /// The maximum length of a string literal.
+    ///
+    /// This is applied to both of the encoded and decoded octets of the string.
+    pub max_string_length: usize,
+
+    /// The maximum ratio of the length of a huffman decoded string to the encoded length.
+    ///
+    /// Because the shortest huffman code is 5 bits, the ratio never exceeds `1.6`.
+    pub max_huffman_expansion_ratio: f64,
+
+    /// The maximum number of dynamic table size updates at the beginning of a header block.
+    pub max_table_size_updates: usize,
+
+    /// If `true`, integers encoded with redundant continuation octets are rejected.
+    ///
+    /// Regardless of this, the number of continuation octets is limited to
+    /// the minimum number needed to represent `max_integer_value`.
+    ///
+    /// The default value is `false`.
+    pub strict_integer_encoding: bool,
+}
+impl DecoderConfig {
+    fn decode_string<'a>(&self, s: HpackString<'a>) -> Result<Cow<'a, [u8]>> {
+        let max_len = (s.len() as f64 * self.max_huffman_expansion_ratio) as usize;
+        let max_len = cmp::min(max_len, self.max_string_length);
+        track!(s.into_plain_bytes(max_len))
+    }
+}
+impl Default for DecoderConfig {
+    fn default() -> Self {
+        DecoderConfig {
+            max_integer_value: u32::MAX,
+            max_header_list_size: usize::MAX,
+            max_field_count: usize::MAX,
+            max_string_length: usize::MAX,
+            max_huffman_expansion_ratio: 1.6,
+            max_table_size_updates: usize::MAX,
+            strict_integer_encoding: false,
+        }
+    }
+}
 
 /// HPACK Decoder.
 #[derive(Debug)]
 pub struct Decoder {
     table: Table,
+    config: DecoderConfig,
+    drain_oversized_header_list: bool,
+    enforce_table_size_updates: bool,
+    pending_hard_limit: Option<PendingHardLimit>,
 }
 impl Decoder {
     /// Makes a new `Decoder` instance.
-    pub fn new(max_dynamic_table_size: u16) -> Self {
+    pub fn new(max_dynamic_table_size: u32) -> Self {
+        Self::with_config(max_dynamic_table_size, DecoderConfig::default())
+    }
+
+    /// Makes a new `Decoder` instance with the given configuration.
+    pub fn with_config(max_dynamic_table_size: u32, config: DecoderConfig) -> Self {
         Decoder {
             table: Table::new(max_dynamic_table_size),
+            config,
+            drain_oversized_header_list: false,
+            enforce_table_size_updates: false,
+            pending_hard_limit: None,
         }
     }
 
+    /// Returns the configuration of this decoder.
+    pub fn config(&self) -> &DecoderConfig {
+        &self.config
+    }
+
+    /// Returns a mutable reference to the configuration of this decoder.
+    pub fn config_mut(&mut self) -> &mut DecoderConfig {
+        &mut self.config
+    }
+
+    /// Returns `true` if this decoder drains the remaining fields of an oversized header list,
+    /// otherwise `false`.
+    pub fn is_oversized_header_list_drained(&self) -> bool {
+        self.drain_oversized_header_list
+    }
+
+    /// Enables or disables the draining of oversized header lists.
+    ///
+    /// If enabled and the maximum size of a header list is exceeded,
+    /// the remaining fields of the block are decoded and dropped before the error is returned.
+    /// Then the indexing table remains in sync with the encoder,
+    /// so the subsequent blocks can still be decoded
+    /// (e.g., an HTTP/2 endpoint can reset only the stream instead of the connection).
+    ///
+    /// If disabled, the decoding stops at the field exceeding the limit,
+    /// and the state of the indexing table becomes undefined.
+    ///
+    /// The default value is `false`.
+    pub fn set_oversized_header_list_drained(&mut self, enabled: bool) {
+        self.drain_oversized_header_list = enabled;
+    }
+
     /// Returns the indexing table of this decoder.
     pub fn table(&self) -> &Table {
         &self.table
     }
 
+    /// Returns `true` if this decoder enforces dynamic table size updates
+    /// after the reductions of the hard limit, otherwise `false`.
+    pub fn is_table_size_update_enforced(&self) -> bool {
+        self.enforce_table_size_updates
+    }
+
+    /// Enables or disables the enforcement of dynamic table size updates.
+    ///
+    /// If enabled, the hard limit of the dynamic table size can be reduced below the soft limit.
+    /// In that case the reduction is regarded as pending until the peer acknowledges it,
+    /// and the next header block must begin with dynamic table size updates that satisfy the following:
+    /// - At least one of them is less than or equal to the smallest hard limit set while pending
+    /// - All of them are less than or equal to the last hard limit
+    ///
+    /// Otherwise, `enter_header_block` method will return an error.
+    ///
+    /// The default value is `false`.
+    ///
+    /// See: [4.2.  Maximum Table Size](https://tools.ietf.org/html/rfc7541#section-4.2)
+    pub fn set_table_size_update_enforced(&mut self, enabled: bool) {
+        self.enforce_table_size_updates = enabled;
+    }
+
+    /// Returns `true` if this decoder is waiting for a dynamic table size update
+    /// that acknowledges a reduction of the hard limit, otherwise `false`.
+    pub fn is_table_size_update_pending(&self) -> bool {
+        self.pending_hard_limit.is_some()
+    }
+
     /// Sets the hard limit of the dynamic table size of this decoder.
     ///
+    /// If the enforcement of dynamic table size updates is enabled and
+    /// `max_size` is less than the soft limit of this decoder,
+    /// the new hard limit will be applied when the next header block is entered
+    /// (see the documentation of `set_table_size_update_enforced` method for more details).
+    ///
     /// # Errors
     ///
-    /// If the value of `max_size` is greater than the soft limit of this decoder
+    /// If the enforcement of dynamic table size updates is disabled and
+    /// the value of `max_size` is less than the soft limit of this decoder
     /// (i.e., the value of `self.table().dynamic().size_soft_limit()`),
     /// an error will be returned.
-    pub fn set_dynamic_table_size_hard_limit(&mut self, max_size: u16) -> Result<()> {
+    pub fn set_dynamic_table_size_hard_limit(&mut self, max_size: u32) -> Result<()> {
+        if self.enforce_table_size_updates {
+            if let Some(ref mut pending) = self.pending_hard_limit {
+                pending.smallest = cmp::min(pending.smallest, max_size);
+                pending.last = max_size;
+            } else if max_size < self.table.dynamic().size_soft_limit() {
+                self.pending_hard_limit = Some(PendingHardLimit {
+                    smallest: max_size,
+                    last: max_size,
+                });
+            } else {
+                self.table.dynamic_mut().set_size_hard_limit(max_size);
+            }
+            return Ok(());
+        }
+
         track_assert!(
             self.table.dynamic().size_soft_limit() <= max_size,
-            Failed,
+            ErrorKind::InvalidInput,
             "soft_limit={}, hard_limit={{old={}, new={}}}",
             self.table.dynamic().size_soft_limit(),
             self.table.dynamic().size_hard_limit(),
@@ -52,18 +232,124 @@ impl Decoder {
         block: &'b [u8],
     ) -> Result<HeaderBlockDecoder<'a, 'b>> {
         let mut reader = SliceReader::new(block);
-        while (track!(reader.peek_u8())? & 0b0010_0000) != 0 {
+        reader.set_limits(&self.config);
+
+        let mut updates = TableSizeUpdates::default();
+        while !reader.eos() && track!(reader.peek_u8())? >> 5 == 0b001 {
             let update = track!(DynamicTableSizeUpdate::decode(&mut reader))?;
-            track!(self
-                .table
-                .dynamic_mut()
-                .set_size_soft_limit(update.max_size,))?;
+            track!(self.handle_table_size_update(update, &mut updates))?;
         }
+        track!(self.finish_table_size_updates(&updates))?;
         Ok(HeaderBlockDecoder {
+            header_list: HeaderList::new(self),
             table: &mut self.table,
             reader,
         })
     }
+
+    /// Returns a `FragmentedHeaderBlockDecoder` instance for decoding a header block
+    /// that is delivered as a sequence of fragments
+    /// (e.g., the payloads of HEADERS and CONTINUATION frames in HTTP/2).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hpack_codec::{Decoder, Encoder};
+    /// use hpack_codec::field::HeaderField;
+    ///
+    /// let mut encoder = Encoder::new(4096);
+    /// let mut header = encoder.enter_header_block(Vec::new()).unwrap();
+    /// header.encode_header(b":method", b"GET").unwrap();
+    /// header.encode_header(b"foo", b"bar").unwrap();
+    /// let encoded_data = header.finish();
+    ///
+    /// let mut decoder = Decoder::new(4096);
+    /// let mut header = decoder.enter_fragmented_header_block();
+    /// let fields = header.feed(&encoded_data[..3]).unwrap();
+    /// assert_eq!(fields, [HeaderField::new(b":method", b"GET").unwrap()]);
+    /// let fields = header.feed(&encoded_data[3..]).unwrap();
+    /// assert_eq!(fields, [HeaderField::new(b"foo", b"bar").unwrap()]);
+    /// header.finish().unwrap();
+    /// ```
+    pub fn enter_fragmented_header_block(&mut self) -> FragmentedHeaderBlockDecoder<'_> {
+        FragmentedHeaderBlockDecoder {
+            header_list: HeaderList::new(self),
+            decoder: self,
+            buf: Vec::new(),
+            buf_required: 0,
+            updates: TableSizeUpdates::default(),
+            in_fields: false,
+            header_list_too_large: false,
+        }
+    }
+
+    fn handle_table_size_update(
+        &mut self,
+        update: DynamicTableSizeUpdate,
+        updates: &mut TableSizeUpdates,
+    ) -> Result<()> {
+        track_assert!(
+            updates.count < self.config.max_table_size_updates,
+            ErrorKind::LimitExceeded,
+            "Too many dynamic table size updates: max={}",
+            self.config.max_table_size_updates
+        );
+        updates.count += 1;
+        if updates.smallest.is_none() {
+            if let Some(pending) = self.pending_hard_limit {
+                self.table.dynamic_mut().set_size_hard_limit(pending.last);
+            }
+        }
+        track_assert!(
+            update.max_size <= self.table.dynamic().size_hard_limit(),
+            ErrorKind::SizeUpdateTooLarge,
+            "update={}, hard_limit={}",
+            update.max_size,
+            self.table.dynamic().size_hard_limit()
+        );
+        track!(self
+            .table
+            .dynamic_mut()
+            .set_size_soft_limit(update.max_size))?;
+        updates.smallest = Some(
+            updates
+                .smallest
+                .map_or(update.max_size, |size| cmp::min(size, update.max_size)),
+        );
+        Ok(())
+    }
+
+    fn finish_table_size_updates(&mut self, updates: &TableSizeUpdates) -> Result<()> {
+        if let Some(pending) = self.pending_hard_limit {
+            let smallest_update = track_assert_some!(
+                updates.smallest,
+                ErrorKind::MissingSizeUpdate,
+                "A dynamic table size update is required: {:?}",
+                pending
+            );
+            track_assert!(
+                smallest_update <= pending.smallest,
+                ErrorKind::SizeUpdateTooLarge,
+                "The smallest table size is not signaled: {:?}, smallest_update={}",
+                pending,
+                smallest_update
+            );
+            self.pending_hard_limit = None;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+struct TableSizeUpdates {
+    count: usize,
+    smallest: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PendingHardLimit {
+    smallest: u32,
+    last: u32,
 }
 
 /// Header Block Decoder.
@@ -71,21 +357,138 @@ impl Decoder {
 pub struct HeaderBlockDecoder<'a, 'b> {
     table: &'a mut Table,
     reader: SliceReader<'b>,
+    header_list: HeaderList,
 }
 impl<'a, 'b: 'a> HeaderBlockDecoder<'a, 'b> {
     /// Decodes a header field.
     ///
     /// If it reached the end of this block, `Ok(None)` will be returned.
-    pub fn decode_field(&mut self) -> Result<Option<HeaderField>> {
-        if let Some(field) = track!(self.decode_raw_field())? {
-            let result = match field {
-                RawHeaderField::Indexed(f) => track!(Self::handle_indexed_field(self.table, f)),
-                RawHeaderField::Literal(f) => track!(Self::handle_literal_field(self.table, f)),
-            };
-            result.map(Some)
+    ///
+    /// # Errors
+    ///
+    /// If the size of the decoded header list exceeds the maximum size,
+    /// an error that has the kind `ErrorKind::HeaderListTooLarge` will be returned.
+    /// See the documentation of `Decoder::set_oversized_header_list_drained` method for
+    /// the state of the indexing table in that case.
+    ///
+    /// If the other resource limits of the decoder are exceeded,
+    /// an error that has the kind `ErrorKind::LimitExceeded` will be returned.
+    pub fn decode_field(&mut self) -> Result<Option<HeaderField<'_>>> {
+        let field = track!(self.decode_field_with_info())?;
+        Ok(field.map(|(field, _)| field))
+    }
+
+    /// Decodes a header field and returns it together with the information about its representation.
+    ///
+    /// This method may be useful for intermediaries that need to re-encode the fields
+    /// while respecting how they arrived
+    /// (see: [6.2.3.  Literal Header Field Never Indexed]
+    ///  (https://tools.ietf.org/html/rfc7541#section-6.2.3)).
+    ///
+    /// # Errors
+    ///
+    /// See the documentation of `decode_field` method.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hpack_codec::Decoder;
+    /// use hpack_codec::field::{FieldRepresentation, LiteralFieldForm, NameSource};
+    ///
+    /// let data = [0x82, 0x1f, 0x11, 0x03, b'f', b'o', b'o'];
+    /// let mut decoder = Decoder::new(4096);
+    /// let mut block = decoder.enter_header_block(&data[..]).unwrap();
+    ///
+    /// let (field, info) = block.decode_field_with_info().unwrap().unwrap();
+    /// assert_eq!(field.name(), b":method");
+    /// assert_eq!(info.representation(), FieldRepresentation::Indexed);
+    /// assert_eq!(info.index().map(|i| i.as_u32()), Some(2));
+    ///
+    /// let (field, info) = block.decode_field_with_info().unwrap().unwrap();
+    /// assert_eq!(field.value(), b"foo");
+    /// assert_eq!(
+    ///     info.representation(),
+    ///     FieldRepresentation::Literal(LiteralFieldForm::NeverIndexed)
+    /// );
+    /// assert_eq!(info.name_source(), NameSource::StaticTable);
+    /// assert!(!info.is_value_huffman_encoded());
+    /// ```
+    pub fn decode_field_with_info(&mut self) -> Result<Option<(HeaderField<'_>, FieldInfo)>> {
+        let (field, info) = if let Some(field) = track!(self.decode_raw_field())? {
+            track!(self.header_list.count_field())?;
+            let info = FieldInfo::new(&field);
+            let field = track!(DecodedField::new(
+                self.table,
+                field,
+                &self.header_list.config
+            ))?;
+            (field, info)
         } else {
-            Ok(None)
+            return Ok(None);
+        };
+
+        let entry_size = track!(field.entry_size(self.table))?;
+        if let Err(e) = track!(self.header_list.add_size(entry_size)) {
+            if self.header_list.drain {
+                while let Some(field) = track!(self.decode_raw_field())? {
+                    track!(self.header_list.count_field())?;
+                    track!(DecodedField::new(
+                        self.table,
+                        field,
+                        &self.header_list.config
+                    ))?;
+                }
+            }
+            return Err(e);
+        }
+        let field = track!(field.into_header_field(self.table))?;
+        Ok(Some((field, info)))
+    }
+
+    /// Decodes all of the remaining header fields in this block.
+    ///
+    /// # Errors
+    ///
+    /// If an error occurs, it is returned and none of the fields are returned.
+    ///
+    /// If the error has the kind `ErrorKind::HeaderListTooLarge`
+    /// and the decoder drains oversized header lists
+    /// (see `Decoder::set_oversized_header_list_drained`),
+    /// the whole block has been consumed and the indexing table remains in sync with the encoder.
+    /// In that case, the error is recoverable:
+    /// only the stream needs to be reset, and the subsequent blocks can still be decoded.
+    ///
+    /// Otherwise, the indexing table may have been updated by the fields preceding the erroneous one,
+    /// so its state is undefined after the error.
+    /// The connection must be treated as broken
+    /// (i.e., a connection error of type `COMPRESSION_ERROR` in HTTP/2),
+    /// and decoding further header blocks with the decoder is not supported.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hpack_codec::{Decoder, Encoder};
+    /// use hpack_codec::field::HeaderField;
+    ///
+    /// let mut encoder = Encoder::new(4096);
+    /// let mut header = encoder.enter_header_block(Vec::new()).unwrap();
+    /// header.encode_header(b":method", b"GET").unwrap();
+    /// header.encode_header(b"foo", b"bar").unwrap();
+    /// let encoded_data = header.finish();
+    ///
+    /// let mut decoder = Decoder::new(4096);
+    /// let fields = decoder.enter_header_block(&encoded_data[..]).unwrap().decode_all().unwrap();
+    /// assert_eq!(fields, [
+    ///     HeaderField::new(b":method", b"GET").unwrap(),
+    ///     HeaderField::new(b"foo", b"bar").unwrap(),
+    /// ]);
+    /// ```
+    pub fn decode_all(&mut self) -> Result<Vec<HeaderField<'static>>> {
+        let mut fields = Vec::new();
+        while let Some(field) = track!(self.decode_field())? {
+            fields.push(field.into_owned());
         }
+        Ok(fields)
     }
 
     /// Decodes a header field and returns the raw representation of it.
@@ -93,6 +496,8 @@ impl<'a, 'b: 'a> HeaderBlockDecoder<'a, 'b> {
     /// This method may be useful for intermediaries
     /// (see: [6.2.3.  Literal Header Field Never Indexed]
     ///  (https://tools.ietf.org/html/rfc7541#section-6.2.3)).
+    ///
+    /// Note that the maximum size of the header list is not applied to the raw representations.
     pub fn decode_raw_field(&mut self) -> Result<Option<RawHeaderField<'b>>> {
         if self.reader.eos() {
             Ok(None)
@@ -103,47 +508,319 @@ impl<'a, 'b: 'a> HeaderBlockDecoder<'a, 'b> {
 
     /// Returns the indexing table of this decoder.
     pub fn table(&self) -> &Table {
-        &self.table
+        self.table
     }
+}
 
-    fn handle_indexed_field(table: &mut Table, field: IndexedHeaderField) -> Result<HeaderField> {
-        track!(table.get(field.index()))
+#[derive(Debug)]
+struct HeaderList {
+    size: usize,
+    field_count: usize,
+    config: DecoderConfig,
+    drain: bool,
+}
+impl HeaderList {
+    fn new(decoder: &Decoder) -> Self {
+        HeaderList {
+            size: 0,
+            field_count: 0,
+            config: decoder.config,
+            drain: decoder.drain_oversized_header_list,
+        }
     }
-    fn handle_literal_field<'c>(
-        table: &'c mut Table,
-        field: LiteralHeaderField<'b>,
-    ) -> Result<HeaderField<'c>>
-    where
-        'b: 'c,
-    {
+    fn count_field(&mut self) -> Result<()> {
+        track_assert!(
+            self.field_count < self.config.max_field_count,
+            ErrorKind::LimitExceeded,
+            "Too many header fields: max={}",
+            self.config.max_field_count
+        );
+        self.field_count += 1;
+        Ok(())
+    }
+    fn add_size(&mut self, entry_size: u32) -> Result<()> {
+        self.size = self.size.saturating_add(entry_size as usize);
+        track_assert!(
+            self.size <= self.config.max_header_list_size,
+            ErrorKind::HeaderListTooLarge,
+            "size>={}, max_size={}",
+            self.size,
+            self.config.max_header_list_size
+        );
+        Ok(())
+    }
+}
+
+// A decoded header field.
+//
+// This does not borrow the indexing table, so the table can be referred to after the decoding.
+#[derive(Debug)]
+enum DecodedField<'b> {
+    Indexed(Index),
+    IndexedName(Index, Cow<'b, [u8]>),
+    Literal(HeaderField<'b>),
+    Evicted(HeaderField<'static>),
+}
+impl<'b> DecodedField<'b> {
+    fn new(table: &mut Table, field: RawHeaderField<'b>, config: &DecoderConfig) -> Result<Self> {
+        let field = match field {
+            RawHeaderField::Indexed(field) => {
+                track!(table.validate_index(field.index()))?;
+                return Ok(DecodedField::Indexed(field.index()));
+            }
+            RawHeaderField::Literal(field) => field,
+        };
+
         let (name, value, form) = field.unwrap();
         if let LiteralFieldForm::WithIndexing = form {
-            let name = match name {
-                FieldName::Index(index) => track!(table.get(index))?.name().to_owned(),
-                FieldName::Name(name) => track!(name.into_plain_bytes())?.into_owned(),
+            let value = track!(config.decode_string(value))?;
+            let evicted = match name {
+                FieldName::Index(index) => track!(table.push_with_indexed_name(index, &value))?,
+                FieldName::Name(name) => {
+                    let name = track!(config.decode_string(name))?;
+                    table.dynamic_mut().push(&name, &value)
+                }
             };
-            let value = track!(value.into_plain_bytes())?.into_owned();
-
-            if let Some(evicted) = table.dynamic_mut().push(name, value) {
-                Ok(evicted)
+            if let Some(evicted) = evicted {
+                Ok(DecodedField::Evicted(evicted))
             } else {
-                let field = table.dynamic().entries()[0].as_borrowed();
-                Ok(field)
+                Ok(DecodedField::Indexed(Index::dynamic_table_offset()))
             }
         } else {
-            let name = match name {
-                FieldName::Index(index) => track!(table.get(index))?.into_cow_name(),
-                FieldName::Name(name) => track!(name.into_plain_bytes())?,
-            };
-            let value = track!(value.into_plain_bytes())?;
-            Ok(HeaderField::from_cow(name, value))
+            let value = track!(config.decode_string(value))?;
+            match name {
+                FieldName::Index(index) => {
+                    track!(table.validate_index(index))?;
+                    Ok(DecodedField::IndexedName(index, value))
+                }
+                FieldName::Name(name) => {
+                    let name = track!(config.decode_string(name))?;
+                    Ok(DecodedField::Literal(HeaderField::from_cow(name, value)))
+                }
+            }
+        }
+    }
+
+    fn entry_size(&self, table: &Table) -> Result<u32> {
+        match *self {
+            DecodedField::Indexed(index) => Ok(track!(table.get(index))?.entry_size()),
+            DecodedField::IndexedName(index, ref value) => {
+                let name = track!(table.get(index))?;
+                Ok((name.name().len() + value.len() + 32) as u32)
+            }
+            DecodedField::Literal(ref field) => Ok(field.entry_size()),
+            DecodedField::Evicted(ref field) => Ok(field.entry_size()),
+        }
+    }
+
+    fn into_header_field<'c>(self, table: &'c Table) -> Result<HeaderField<'c>>
+    where
+        'b: 'c,
+    {
+        match self {
+            DecodedField::Indexed(index) => track!(table.get(index)),
+            DecodedField::IndexedName(index, value) => {
+                Ok(track!(table.get(index))?.with_value(value))
+            }
+            DecodedField::Literal(field) => Ok(field),
+            DecodedField::Evicted(field) => Ok(field),
+        }
+    }
+}
+
+/// Header Block Decoder that accepts a header block as a sequence of fragments.
+///
+/// Fragments are not required to be aligned with the boundaries of representations.
+/// Only the incomplete tail of a fragment is buffered until the subsequent fragments are fed.
+/// Once the length prefixes of the buffered representation have been received,
+/// it is not parsed again until all of the remaining octets arrive.
+#[derive(Debug)]
+pub struct FragmentedHeaderBlockDecoder<'a> {
+    decoder: &'a mut Decoder,
+    buf: Vec<u8>,
+    buf_required: usize,
+    updates: TableSizeUpdates,
+    in_fields: bool,
+    header_list: HeaderList,
+    header_list_too_large: bool,
+}
+impl<'a> FragmentedHeaderBlockDecoder<'a> {
+    /// Feeds a fragment of the header block to this decoder.
+    ///
+    /// The header fields completed by the fragment are returned in the order of appearance.
+    ///
+    /// # Errors
+    ///
+    /// If the size of the decoded header list exceeds the maximum size and
+    /// the oversized header lists are not drained by the decoder,
+    /// an error that has the kind `ErrorKind::HeaderListTooLarge` will be returned.
+    /// Otherwise the exceeding fields are dropped and the error is reported by `finish` method.
+    pub fn feed(&mut self, fragment: &[u8]) -> Result<Vec<HeaderField<'static>>> {
+        let mut fields = Vec::new();
+        let mut fragment = fragment;
+        if !self.buf.is_empty() {
+            let buf_len = self.buf.len();
+            self.buf.extend_from_slice(fragment);
+            if self.buf.len() < self.buf_required {
+                return Ok(fields);
+            }
+            let buf = std::mem::take(&mut self.buf);
+            let result = self.decode_fields(&buf, &mut fields, true);
+            self.buf = buf;
+            let consumed = track!(result)?;
+            if consumed < buf_len {
+                self.buf.drain(..consumed);
+                self.buf_required = required_len(&self.buf);
+                return Ok(fields);
+            }
+            self.buf.clear();
+            fragment = &fragment[consumed - buf_len..];
+        }
+
+        let consumed = track!(self.decode_fields(fragment, &mut fields, false))?;
+        self.buf.extend_from_slice(&fragment[consumed..]);
+        self.buf_required = required_len(&self.buf);
+        Ok(fields)
+    }
+
+    /// Finishes the decoding of the header block.
+    ///
+    /// # Errors
+    ///
+    /// If the fed fragments end in the middle of a representation, an error will be returned.
+    ///
+    /// If the exceeding fields of an oversized header list have been dropped,
+    /// an error that has the kind `ErrorKind::HeaderListTooLarge` will be returned.
+    pub fn finish(mut self) -> Result<()> {
+        track_assert!(
+            self.buf.is_empty(),
+            ErrorKind::TruncatedInput,
+            "{} octets remain undecoded",
+            self.buf.len()
+        );
+        if !self.in_fields {
+            track!(self.enter_fields())?;
+        }
+        track_assert!(
+            !self.header_list_too_large,
+            ErrorKind::HeaderListTooLarge,
+            "max_size={}",
+            self.header_list.config.max_header_list_size
+        );
+        Ok(())
+    }
+
+    /// Returns the indexing table of this decoder.
+    pub fn table(&self) -> &Table {
+        &self.decoder.table
+    }
+
+    // Decodes the complete representations in `data`, and returns the number of consumed octets.
+    //
+    // If `only_first` is `true`, the decoding stops after the first representation.
+    fn decode_fields(
+        &mut self,
+        data: &[u8],
+        fields: &mut Vec<HeaderField<'static>>,
+        only_first: bool,
+    ) -> Result<usize> {
+        let mut reader = SliceReader::new(data);
+        reader.set_limits(&self.decoder.config);
+        while !reader.eos() {
+            let position = reader.position();
+            if !self.in_fields && track!(reader.peek_u8())? >> 5 == 0b001 {
+                let update = match DynamicTableSizeUpdate::decode(&mut reader) {
+                    Err(ref e) if *e.kind() == ErrorKind::TruncatedInput => return Ok(position),
+                    result => track!(result)?,
+                };
+                track!(self
+                    .decoder
+                    .handle_table_size_update(update, &mut self.updates))?;
+            } else {
+                if !self.in_fields {
+                    track!(self.enter_fields())?;
+                }
+                let field = match RawHeaderField::decode(&mut reader) {
+                    Err(ref e) if *e.kind() == ErrorKind::TruncatedInput => return Ok(position),
+                    result => track!(result)?,
+                };
+                track!(self.header_list.count_field())?;
+                let config = &self.header_list.config;
+                let field = track!(DecodedField::new(&mut self.decoder.table, field, config))?;
+                if self.header_list_too_large {
+                    // Drains the remaining fields
+                } else {
+                    let entry_size = track!(field.entry_size(&self.decoder.table))?;
+                    if let Err(e) = track!(self.header_list.add_size(entry_size)) {
+                        if !self.header_list.drain {
+                            return Err(e);
+                        }
+                        self.header_list_too_large = true;
+                    } else {
+                        let field = track!(field.into_header_field(&self.decoder.table))?;
+                        fields.push(field.into_owned());
+                    }
+                }
+            }
+            if only_first {
+                break;
+            }
+        }
+        Ok(reader.position())
+    }
+
+    fn enter_fields(&mut self) -> Result<()> {
+        track!(self.decoder.finish_table_size_updates(&self.updates))?;
+        self.in_fields = true;
+        Ok(())
+    }
+}
+
+// Returns the number of octets required to complete the representation at the beginning of `data`.
+//
+// If a length prefix of the representation is incomplete,
+// the length of `data` plus one (i.e., a lower bound of the required length) is returned.
+fn required_len(data: &[u8]) -> usize {
+    if data.is_empty() {
+        return 0;
+    }
+    let incomplete = data.len() + 1;
+    let (prefix_bits, has_name) = match data[0] {
+        b if b >> 7 == 0b1 => (7, false),
+        b if b >> 6 == 0b01 => (6, true),
+        b if b >> 5 == 0b001 => (5, false),
+        _ => (4, true),
+    };
+    let mut reader = SliceReader::new(data);
+    let index = match reader.read_integer(prefix_bits) {
+        Ok((_, index)) => index,
+        Err(_) => return incomplete,
+    };
+    let strings = match (has_name, index) {
+        (false, _) => 0,
+        (true, 0) => 2,
+        (true, _) => 1,
+    };
+    let mut required = reader.position();
+    for _ in 0..strings {
+        let len = match reader.read_integer(7) {
+            Ok((_, len)) => len as usize,
+            Err(_) => return incomplete,
+        };
+        required = reader.position() + len;
+        if data.len() < required {
+            break;
         }
+        reader.consume(len);
     }
+    required
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::literal::HuffmanCoding;
+    use crate::Encoder;
 
     macro_rules! assert_decode {
         ($decoder:expr, $key:expr, $value:expr) => {{
@@ -164,24 +841,24 @@ mod tests {
     fn literal_header_field_with_indexing() {
         let mut decoder = Decoder::new(4096);
         {
-            let data;
-            #[cfg_attr(rustfmt, rustfmt_skip)]
-            {
-                data = [
-                    0x40, 0x0a, 0x63, 0x75, 0x73, 0x74, 0x6f, 0x6d, 0x2d,
-                    0x6b, 0x65, 0x79, 0x0d, 0x63, 0x75, 0x73, 0x74, 0x6f,
-                    0x6d, 0x2d, 0x68, 0x65, 0x61, 0x64, 0x65, 0x72,
-                ];
-            }
+            #[rustfmt::skip]
+            let data = [
+                0x40, 0x0a, 0x63, 0x75, 0x73, 0x74, 0x6f, 0x6d, 0x2d,
+                0x6b, 0x65, 0x79, 0x0d, 0x63, 0x75, 0x73, 0x74, 0x6f,
+                0x6d, 0x2d, 0x68, 0x65, 0x61, 0x64, 0x65, 0x72,
+            ];
             let mut block = track_try_unwrap!(decoder.enter_header_block(&data[..]));
             assert_decode!(block, b"custom-key", b"custom-header");
             assert_eob!(block);
         }
         assert_eq!(decoder.table.dynamic().entries().len(), 1);
         assert_eq!(decoder.table.dynamic().size(), 55);
-        assert_eq!(decoder.table.dynamic().entries()[0].name(), b"custom-key");
         assert_eq!(
-            decoder.table.dynamic().entries()[0].value(),
+            decoder.table.dynamic().entry(0).unwrap().name(),
+            b"custom-key"
+        );
+        assert_eq!(
+            decoder.table.dynamic().entry(0).unwrap().value(),
             b"custom-header"
         );
     }
@@ -191,14 +868,11 @@ mod tests {
     fn literal_header_field_without_indexing() {
         let mut decoder = Decoder::new(4096);
         {
-            let data;
-            #[cfg_attr(rustfmt, rustfmt_skip)]
-            {
-                data = [
-                    0x04, 0x0c, 0x2f, 0x73, 0x61, 0x6d, 0x70,
-                    0x6c, 0x65, 0x2f, 0x70, 0x61, 0x74, 0x68
-                ];
-            }
+            #[rustfmt::skip]
+            let data = [
+                0x04, 0x0c, 0x2f, 0x73, 0x61, 0x6d, 0x70,
+                0x6c, 0x65, 0x2f, 0x70, 0x61, 0x74, 0x68
+            ];
             let mut block = track_try_unwrap!(decoder.enter_header_block(&data[..]));
             assert_decode!(block, b":path", b"/sample/path");
             assert_eob!(block);
@@ -211,14 +885,11 @@ mod tests {
     fn literal_header_field_never_indexed() {
         let mut decoder = Decoder::new(4096);
         {
-            let data;
-            #[cfg_attr(rustfmt, rustfmt_skip)]
-            {
-                data = [
-                    0x10, 0x08, 0x70, 0x61, 0x73, 0x73, 0x77, 0x6f, 0x72,
-                    0x64, 0x06, 0x73, 0x65, 0x63, 0x72, 0x65, 0x74
-                ];
-            }
+            #[rustfmt::skip]
+            let data = [
+                0x10, 0x08, 0x70, 0x61, 0x73, 0x73, 0x77, 0x6f, 0x72,
+                0x64, 0x06, 0x73, 0x65, 0x63, 0x72, 0x65, 0x74
+            ];
             let mut block = track_try_unwrap!(decoder.enter_header_block(&data[..]));
             assert_decode!(block, b"password", b"secret");
             assert_eob!(block);
@@ -226,6 +897,402 @@ mod tests {
         assert_eq!(decoder.table.dynamic().entries().len(), 0);
     }
 
+    #[test]
+    fn large_header_field() {
+        let value = vec![b'a'; 100_000];
+        let mut encoder = Encoder::new(200_000);
+        let mut block = track_try_unwrap!(encoder.enter_header_block(Vec::new()));
+        track_try_unwrap!(block.encode_header(b"cookie", &value));
+        let data = block.finish();
+        assert_eq!(encoder.table().dynamic().size(), 100_038);
+
+        let mut decoder = Decoder::new(200_000);
+        {
+            let mut block = track_try_unwrap!(decoder.enter_header_block(&data[..]));
+            assert_decode!(block, b"cookie", &value[..]);
+            assert_eob!(block);
+        }
+        assert_eq!(decoder.table.dynamic().size(), 100_038);
+
+        let mut decoder = Decoder::new(200_000);
+        decoder.config_mut().max_integer_value = 65_535;
+        let mut block = track_try_unwrap!(decoder.enter_header_block(&data[..]));
+        assert!(block.decode_field().is_err());
+    }
+
+    #[test]
+    fn enforced_table_size_update() {
+        let mut encoder = Encoder::new(4096);
+        let mut decoder = Decoder::new(4096);
+        decoder.set_table_size_update_enforced(true);
+
+        // Raising the hard limit does not require updates
+        track_try_unwrap!(decoder.set_dynamic_table_size_hard_limit(8192));
+        assert!(!decoder.is_table_size_update_pending());
+        assert_eq!(decoder.table().dynamic().size_hard_limit(), 8192);
+
+        track_try_unwrap!(decoder.set_dynamic_table_size_hard_limit(1024));
+        track_try_unwrap!(decoder.set_dynamic_table_size_hard_limit(2048));
+        assert!(decoder.is_table_size_update_pending());
+
+        // Missing update
+        assert!(decoder.enter_header_block(&[0x82][..]).is_err());
+
+        // The smallest size is not signaled
+        assert!(decoder.enter_header_block(&[0x3f, 0xe1, 0x0f][..]).is_err());
+
+        // Too large update
+        assert!(decoder
+            .enter_header_block(&[0x3f, 0xe1, 0x07, 0x3f, 0xe1, 0x1f][..])
+            .is_err());
+
+        // Compliant updates
+        encoder.set_dynamic_table_size_hard_limit(1024);
+        encoder.set_dynamic_table_size_hard_limit(2048);
+        track_try_unwrap!(encoder.set_dynamic_table_size_soft_limit(2048));
+        let mut block = track_try_unwrap!(encoder.enter_header_block(Vec::new()));
+        track_try_unwrap!(block.encode_header(b"foo", b"bar"));
+        let data = block.finish();
+        {
+            let mut block = track_try_unwrap!(decoder.enter_header_block(&data[..]));
+            assert_decode!(block, b"foo", b"bar");
+            assert_eob!(block);
+        }
+        assert!(!decoder.is_table_size_update_pending());
+        assert_eq!(decoder.table().dynamic().size_hard_limit(), 2048);
+        assert_eq!(decoder.table().dynamic().size_soft_limit(), 2048);
+
+        // Subsequent blocks do not require updates
+        track_try_unwrap!(decoder.enter_header_block(&[0x82][..]));
+    }
+
+    #[test]
+    fn fragmented_header_block() {
+        let mut encoder = Encoder::new(4096);
+        encoder.set_huffman_coding(HuffmanCoding::Always);
+        track_try_unwrap!(encoder.set_dynamic_table_size_soft_limit(300));
+        let headers = [
+            (&b":method"[..], &b"GET"[..]),
+            (b":path", b"/index.html"),
+            (b"custom-key", b"custom-value"),
+            (b"cookie", &[b'a'; 200][..]),
+            (b"custom-key", b"custom-value"),
+        ];
+        let mut block = track_try_unwrap!(encoder.enter_header_block(Vec::new()));
+        track_try_unwrap!(block.encode_headers(headers.iter().cloned()));
+        let data = block.finish();
+
+        for fragment_size in 1..data.len() + 1 {
+            let mut decoder = Decoder::new(4096);
+            let mut fields = Vec::new();
+            {
+                let mut block = decoder.enter_fragmented_header_block();
+                for fragment in data.chunks(fragment_size) {
+                    fields.extend(track_try_unwrap!(block.feed(fragment)));
+                }
+                track_try_unwrap!(block.finish());
+            }
+            assert_eq!(fields.len(), headers.len());
+            for (field, &(name, value)) in fields.iter().zip(headers.iter()) {
+                assert_eq!(field.name(), name);
+                assert_eq!(field.value(), value);
+            }
+            assert_eq!(decoder.table().dynamic().size_soft_limit(), 300);
+            assert_eq!(decoder.table().dynamic().size(), 292);
+        }
+
+        // Truncated
+        let mut decoder = Decoder::new(4096);
+        let mut block = decoder.enter_fragmented_header_block();
+        track_try_unwrap!(block.feed(&data[..data.len() - 2]));
+        assert_eq!(
+            *block.finish().err().unwrap().kind(),
+            ErrorKind::TruncatedInput
+        );
+    }
+
+    #[test]
+    fn fragmented_long_literal() {
+        let value = [b'a'; 300];
+        let mut data = vec![0x00, 0x03, b'f', b'o', b'o', 0x7f, 0xad, 0x01];
+        data.extend_from_slice(&value);
+
+        let mut decoder = Decoder::new(4096);
+        let mut block = decoder.enter_fragmented_header_block();
+        let mut fields = Vec::new();
+        for (i, octet) in data.chunks(1).enumerate() {
+            fields.extend(track_try_unwrap!(block.feed(octet)));
+            match i {
+                0 => assert_eq!(block.buf_required, 2), // name length prefix
+                1..=3 => assert_eq!(block.buf_required, 5), // name
+                4..=6 => assert_eq!(block.buf_required, i + 2), // value length prefix
+                7..=306 => assert_eq!(block.buf_required, data.len()), // value
+                _ => assert_eq!(block.buf_required, 0),
+            }
+        }
+        track_try_unwrap!(block.finish());
+        assert_eq!(fields, [HeaderField::new(b"foo", &value).unwrap()]);
+    }
+
+    #[test]
+    fn max_header_list_size() {
+        let value = vec![b'a'; 1000];
+        let mut encoder = Encoder::new(4096);
+        let mut block = track_try_unwrap!(encoder.enter_header_block(Vec::new()));
+        for _ in 0..10 {
+            track_try_unwrap!(block.encode_header(b"cookie", &value));
+        }
+        track_try_unwrap!(block.encode_header(b"foo", b"bar"));
+        let data = block.finish();
+        assert_eq!(encoder.table().dynamic().entries().len(), 2);
+
+        let mut block = track_try_unwrap!(encoder.enter_header_block(Vec::new()));
+        track_try_unwrap!(block.encode_header(b"foo", b"bar"));
+        let next_data = block.finish();
+
+        // Not drained
+        let mut decoder = Decoder::new(4096);
+        decoder.config_mut().max_header_list_size = 4000;
+        {
+            let mut block = track_try_unwrap!(decoder.enter_header_block(&data[..]));
+            for _ in 0..3 {
+                assert_decode!(block, b"cookie", &value[..]);
+            }
+            let e = block.decode_field().err().unwrap();
+            assert_eq!(*e.kind(), ErrorKind::HeaderListTooLarge);
+        }
+
+        // Drained
+        let mut decoder = Decoder::new(4096);
+        decoder.config_mut().max_header_list_size = 4000;
+        decoder.set_oversized_header_list_drained(true);
+        {
+            let mut block = track_try_unwrap!(decoder.enter_header_block(&data[..]));
+            for _ in 0..3 {
+                assert_decode!(block, b"cookie", &value[..]);
+            }
+            let e = block.decode_field().err().unwrap();
+            assert_eq!(*e.kind(), ErrorKind::HeaderListTooLarge);
+        }
+        {
+            let mut block = track_try_unwrap!(decoder.enter_header_block(&next_data[..]));
+            assert_decode!(block, b"foo", b"bar");
+            assert_eob!(block);
+        }
+
+        // Drained (`decode_all`)
+        let mut decoder = Decoder::new(4096);
+        decoder.config_mut().max_header_list_size = 4000;
+        decoder.set_oversized_header_list_drained(true);
+        {
+            let mut block = track_try_unwrap!(decoder.enter_header_block(&data[..]));
+            let e = block.decode_all().err().unwrap();
+            assert_eq!(*e.kind(), ErrorKind::HeaderListTooLarge);
+        }
+        {
+            let mut block = track_try_unwrap!(decoder.enter_header_block(&next_data[..]));
+            let fields = track_try_unwrap!(block.decode_all());
+            assert_eq!(fields, [HeaderField::new(b"foo", b"bar").unwrap()]);
+        }
+
+        // Fragmented
+        let mut decoder = Decoder::new(4096);
+        decoder.config_mut().max_header_list_size = 4000;
+        {
+            let mut block = decoder.enter_fragmented_header_block();
+            let e = block.feed(&data).err().unwrap();
+            assert_eq!(*e.kind(), ErrorKind::HeaderListTooLarge);
+        }
+
+        let mut decoder = Decoder::new(4096);
+        decoder.config_mut().max_header_list_size = 4000;
+        decoder.set_oversized_header_list_drained(true);
+        {
+            let mut block = decoder.enter_fragmented_header_block();
+            let fields = track_try_unwrap!(block.feed(&data));
+            assert_eq!(fields.len(), 3);
+            let e = block.finish().err().unwrap();
+            assert_eq!(*e.kind(), ErrorKind::HeaderListTooLarge);
+        }
+        {
+            let mut block = decoder.enter_fragmented_header_block();
+            let fields = track_try_unwrap!(block.feed(&next_data));
+            assert_eq!(fields, [HeaderField::new(b"foo", b"bar").unwrap()]);
+            track_try_unwrap!(block.finish());
+        }
+    }
+
+    #[test]
+    fn resource_limits() {
+        fn decode(config: DecoderConfig, data: &[u8]) -> Result<usize> {
+            let mut decoder = Decoder::with_config(4096, config);
+            let mut block = track!(decoder.enter_header_block(data))?;
+            let mut count = 0;
+            while track!(block.decode_field())?.is_some() {
+                count += 1;
+            }
+            Ok(count)
+        }
+        fn error_kind(result: Result<usize>) -> ErrorKind {
+            *result.err().unwrap().kind()
+        }
+
+        let mut encoder = Encoder::new(4096);
+        encoder.set_huffman_coding(HuffmanCoding::Always);
+        let mut block = track_try_unwrap!(encoder.enter_header_block(vec![0x20, 0x3f, 0xe1, 0x1f]));
+        track_try_unwrap!(block.encode_header(b":method", b"GET"));
+        track_try_unwrap!(block.encode_header(b"foo", b"0000000000"));
+        track_try_unwrap!(block.encode_header(b"foo", b"0000000000"));
+        let data = block.finish();
+        assert_eq!(
+            track_try_unwrap!(decode(DecoderConfig::default(), &data)),
+            3
+        );
+
+        let config = DecoderConfig {
+            max_field_count: 3,
+            max_string_length: 10,
+            max_huffman_expansion_ratio: 1.6,
+            max_table_size_updates: 2,
+            ..DecoderConfig::default()
+        };
+        assert_eq!(track_try_unwrap!(decode(config, &data)), 3);
+
+        let c = DecoderConfig {
+            max_field_count: 2,
+            ..config
+        };
+        assert_eq!(error_kind(decode(c, &data)), ErrorKind::LimitExceeded);
+
+        let c = DecoderConfig {
+            max_string_length: 9,
+            ..config
+        };
+        assert_eq!(error_kind(decode(c, &data)), ErrorKind::LimitExceeded);
+
+        // "0000000000" is encoded into 7 octets
+        let c = DecoderConfig {
+            max_huffman_expansion_ratio: 1.4,
+            ..config
+        };
+        assert_eq!(error_kind(decode(c, &data)), ErrorKind::LimitExceeded);
+
+        let c = DecoderConfig {
+            max_table_size_updates: 1,
+            ..config
+        };
+        assert_eq!(error_kind(decode(c, &data)), ErrorKind::LimitExceeded);
+    }
+
+    #[test]
+    fn decode_all() {
+        let mut decoder = Decoder::new(4096);
+        let fields = {
+            let data = [0x82, 0x84];
+            let mut block = track_try_unwrap!(decoder.enter_header_block(&data[..]));
+            track_try_unwrap!(block.decode_all())
+        };
+        assert_eq!(fields.len(), 2);
+        assert_eq!(fields[0].name(), b":method");
+        assert_eq!(fields[1].name(), b":path");
+
+        // Invalid index
+        let data = [0x82, 0xbe];
+        let mut block = track_try_unwrap!(decoder.enter_header_block(&data[..]));
+        assert!(block.decode_all().is_err());
+
+        // No partial result is returned even if the table has been updated
+        let data = [0x40, 0x03, b'f', b'o', b'o', 0x03, b'b', b'a', b'r', 0xbf];
+        let result = {
+            let mut block = track_try_unwrap!(decoder.enter_header_block(&data[..]));
+            block.decode_all()
+        };
+        assert_eq!(
+            result.err().map(|e| *e.kind()),
+            Some(ErrorKind::InvalidIndex)
+        );
+        assert_eq!(decoder.table().dynamic().len(), 1);
+    }
+
+    #[test]
+    fn decode_field_with_info() {
+        use crate::field::{FieldRepresentation, LiteralHeaderField, NameSource};
+
+        let mut encoder = Encoder::new(4096);
+        let mut block = track_try_unwrap!(encoder.enter_header_block(Vec::new()));
+        let field = LiteralHeaderField::new(b"foo", b"bar")
+            .with_indexing()
+            .with_huffman_encoded_name();
+        track_try_unwrap!(block.encode_field(field));
+        let field = LiteralHeaderField::with_indexed_name(Index::dynamic_table_offset(), b"baz")
+            .with_huffman_encoded_value();
+        track_try_unwrap!(block.encode_field(field));
+        track_try_unwrap!(block.encode_field(Index::dynamic_table_offset()));
+        let data = block.finish();
+
+        let mut decoder = Decoder::new(4096);
+        let mut block = track_try_unwrap!(decoder.enter_header_block(&data[..]));
+
+        let (field, info) = track_try_unwrap!(block.decode_field_with_info()).unwrap();
+        assert_eq!(field, track_try_unwrap!(HeaderField::new(b"foo", b"bar")));
+        assert_eq!(
+            info.representation(),
+            FieldRepresentation::Literal(LiteralFieldForm::WithIndexing)
+        );
+        assert_eq!(info.index(), None);
+        assert_eq!(info.name_source(), NameSource::Literal);
+        assert!(info.is_name_huffman_encoded());
+        assert!(!info.is_value_huffman_encoded());
+
+        let (field, info) = track_try_unwrap!(block.decode_field_with_info()).unwrap();
+        assert_eq!(field, track_try_unwrap!(HeaderField::new(b"foo", b"baz")));
+        assert_eq!(
+            info.representation(),
+            FieldRepresentation::Literal(LiteralFieldForm::WithoutIndexing)
+        );
+        assert_eq!(info.index(), Some(Index::dynamic_table_offset()));
+        assert_eq!(info.name_source(), NameSource::DynamicTable);
+        assert!(!info.is_name_huffman_encoded());
+        assert!(info.is_value_huffman_encoded());
+
+        let (field, info) = track_try_unwrap!(block.decode_field_with_info()).unwrap();
+        assert_eq!(field, track_try_unwrap!(HeaderField::new(b"foo", b"bar")));
+        assert_eq!(info.representation(), FieldRepresentation::Indexed);
+        assert_eq!(info.index(), Some(Index::dynamic_table_offset()));
+        assert_eq!(info.name_source(), NameSource::DynamicTable);
+
+        assert!(track_try_unwrap!(block.decode_field_with_info()).is_none());
+    }
+
+    #[test]
+    fn dynamic_table_entries_outlive_eviction() {
+        let mut decoder = Decoder::new(4096);
+        let data = [0x40, 0x03, b'f', b'o', b'o', 0x03, b'b', b'a', b'r', 0xbe];
+        let fields = {
+            let mut block = track_try_unwrap!(decoder.enter_header_block(&data[..]));
+            track_try_unwrap!(block.decode_all())
+        };
+        assert_eq!(fields.len(), 2);
+
+        // The decoded fields hold their own copies of the entry
+        let entry = decoder.table().dynamic().entry(0).unwrap();
+        assert_ne!(fields[0].value().as_ptr(), entry.value().as_ptr());
+        assert_ne!(fields[1].value().as_ptr(), fields[0].value().as_ptr());
+
+        // Cloning a field does not copy the octets
+        let cloned = fields[1].clone();
+        assert_eq!(cloned.value().as_ptr(), fields[1].value().as_ptr());
+
+        // The fields outlive the eviction
+        track_try_unwrap!(decoder.enter_header_block(&[0x20][..]));
+        assert!(decoder.table().dynamic().is_empty());
+        assert_eq!(
+            fields[0],
+            track_try_unwrap!(HeaderField::new(b"foo", b"bar"))
+        );
+        assert_eq!(fields[1], fields[0]);
+    }
+
     #[test]
     /// https://tools.ietf.org/html/rfc7541#appendix-C.2.4
     fn indexed_header_field() {
@@ -236,6 +1303,6 @@ mod tests {
             assert_decode!(block, b":method", b"GET");
             assert_eob!(block);
         }
-        assert!(decoder.table.dynamic().entries().is_empty());
+        assert!(decoder.table.dynamic().is_empty());
     }
 }
@@ -1,21 +1,28 @@
-use crate::field::{FieldName, LiteralFieldForm, RawHeaderField};
+use crate::field::{FieldName, LiteralFieldForm, LiteralHeaderField, RawHeaderField};
+use crate::literal::HuffmanCoding;
+use crate::policy::{AlwaysIndexing, IndexingPolicy};
 use crate::signal::DynamicTableSizeUpdate;
 use crate::table::Table;
-use crate::Result;
-use std::io::Write;
+use crate::{ErrorKind, Result};
+use std::cmp;
+use std::io::{self, Write};
 
 /// HPACK Encoder.
 #[derive(Debug)]
 pub struct Encoder {
     table: Table,
-    dynamic_table_size_updates: Vec<u16>,
+    smallest_pending_table_size: Option<u32>,
+    indexing_policy: Box<dyn IndexingPolicy + Send + Sync + 'static>,
+    huffman_coding: HuffmanCoding,
 }
 impl Encoder {
     /// Makes a new `Encoder` instance.
-    pub fn new(max_dynamic_table_size: u16) -> Self {
+    pub fn new(max_dynamic_table_size: u32) -> Self {
         Encoder {
-            table: Table::new(max_dynamic_table_size),
-            dynamic_table_size_updates: Vec::new(),
+            table: Table::with_field_index(max_dynamic_table_size),
+            smallest_pending_table_size: None,
+            indexing_policy: Box::new(AlwaysIndexing),
+            huffman_coding: HuffmanCoding::default(),
         }
     }
 
@@ -24,13 +31,43 @@ impl Encoder {
         &self.table
     }
 
+    /// Returns the indexing policy of this encoder.
+    pub fn indexing_policy(&self) -> &(dyn IndexingPolicy + Send + Sync) {
+        &*self.indexing_policy
+    }
+
+    /// Sets the indexing policy of this encoder.
+    ///
+    /// The policy is consulted by `HeaderBlockEncoder::encode_header` method.
+    /// The default policy is `AlwaysIndexing`.
+    pub fn set_indexing_policy<P>(&mut self, policy: P)
+    where
+        P: IndexingPolicy + Send + Sync + 'static,
+    {
+        self.indexing_policy = Box::new(policy);
+    }
+
+    /// Returns the huffman coding strategy of this encoder.
+    pub fn huffman_coding(&self) -> HuffmanCoding {
+        self.huffman_coding
+    }
+
+    /// Sets the huffman coding strategy of this encoder.
+    ///
+    /// The strategy is applied to the string literals encoded by `HeaderBlockEncoder::encode_header` method.
+    /// The default strategy is `HuffmanCoding::Never`.
+    pub fn set_huffman_coding(&mut self, coding: HuffmanCoding) {
+        self.huffman_coding = coding;
+    }
+
     /// Sets the hard limit of the dynamic table size of this encoder.
-    pub fn set_dynamic_table_size_hard_limit(&mut self, max_size: u16) {
+    ///
+    /// If the soft limit is changed by this (i.e., `max_size` is less than the current soft limit),
+    /// the change will be signaled at the beginning of the next header block.
+    pub fn set_dynamic_table_size_hard_limit(&mut self, max_size: u32) {
         let old = self.table.dynamic().size_soft_limit();
         self.table.dynamic_mut().set_size_hard_limit(max_size);
-        if old != self.table.dynamic().size_soft_limit() {
-            self.dynamic_table_size_updates.push(old);
-        }
+        self.note_table_size_change(old);
     }
 
     /// Sets the soft limit of the dynamic table size of this encoder.
@@ -38,32 +75,140 @@ impl Encoder {
     /// # Errors
     ///
     /// If `max_size` exceeds the hard limit of this, an error will be returned.
-    pub fn set_dynamic_table_size_soft_limit(&mut self, max_size: u16) -> Result<()> {
+    pub fn set_dynamic_table_size_soft_limit(&mut self, max_size: u32) -> Result<()> {
         let old = self.table.dynamic().size_soft_limit();
         track!(self.table.dynamic_mut().set_size_soft_limit(max_size))?;
-        if old != self.table.dynamic().size_soft_limit() {
-            self.dynamic_table_size_updates.push(old);
-        }
+        self.note_table_size_change(old);
         Ok(())
     }
 
     /// Returns a `HeaderBlockEncoder` instance for encoding header fields to the `block`.
-    pub fn enter_header_block<W: Write>(&mut self, mut block: W) -> Result<HeaderBlockEncoder<W>> {
-        for max_size in self.dynamic_table_size_updates.drain(..) {
-            let update = DynamicTableSizeUpdate { max_size };
+    ///
+    /// If the size of the dynamic table has been changed since the previous header block,
+    /// dynamic table size updates are written at the beginning of `block`.
+    /// The smallest size reached in the interval is signaled first (if it is needed),
+    /// and then the final size is signaled.
+    ///
+    /// The changes of the state of this encoder made by the returned instance
+    /// (i.e., the insertions into the dynamic table and the signaling of the size updates)
+    /// take effect only when `HeaderBlockEncoder::commit` (or `finish`) method is called,
+    /// and they are discarded if the instance is dropped without calling it.
+    /// See the documentation of `HeaderBlockEncoder` for more details.
+    ///
+    /// See: [4.2.  Maximum Table Size](https://tools.ietf.org/html/rfc7541#section-4.2)
+    pub fn enter_header_block<W: Write>(
+        &mut self,
+        mut block: W,
+    ) -> Result<HeaderBlockEncoder<'_, W>> {
+        let pending_table_size = self.smallest_pending_table_size;
+        if let Some(smallest) = pending_table_size {
+            let last = self.table.dynamic().size_soft_limit();
+            if smallest < last {
+                let update = DynamicTableSizeUpdate { max_size: smallest };
+                track!(update.encode(&mut block))?;
+            }
+            let update = DynamicTableSizeUpdate { max_size: last };
             track!(update.encode(&mut block))?;
+            self.smallest_pending_table_size = None;
         }
+        self.table.dynamic_mut().begin();
         Ok(HeaderBlockEncoder {
-            table: &mut self.table,
+            transaction: Transaction {
+                table: &mut self.table,
+                smallest_pending_table_size: &mut self.smallest_pending_table_size,
+                pending_table_size,
+            },
+            indexing_policy: &*self.indexing_policy,
+            huffman_coding: self.huffman_coding,
             block,
         })
     }
+
+    /// Returns a `HeaderBlockEncoder` instance that splits the encoded header block into fragments.
+    ///
+    /// The length of the first fragment does not exceed `first_fragment_size`,
+    /// and the lengths of the subsequent fragments do not exceed `fragment_size`.
+    /// In HTTP/2, the fragments can be sent as the payloads of a HEADERS frame and
+    /// the following CONTINUATION frames respectively.
+    ///
+    /// See the documentation of `FragmentWriter` for more details.
+    ///
+    /// # Errors
+    ///
+    /// If `fragment_size` is zero, an error will be returned.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hpack_codec::Encoder;
+    ///
+    /// let mut encoder = Encoder::new(4096);
+    /// let mut header = encoder.enter_fragmented_header_block(4, 8).unwrap();
+    /// header.encode_header(b":method", b"GET").unwrap();
+    /// header.encode_header(b"foo", b"bar").unwrap();
+    /// let fragments = header.finish().into_fragments();
+    /// assert_eq!(fragments.iter().map(|f| f.len()).collect::<Vec<_>>(), [4, 6]);
+    /// ```
+    pub fn enter_fragmented_header_block(
+        &mut self,
+        first_fragment_size: usize,
+        fragment_size: usize,
+    ) -> Result<HeaderBlockEncoder<'_, FragmentWriter>> {
+        let writer = track!(FragmentWriter::new(first_fragment_size, fragment_size))?;
+        track!(self.enter_header_block(writer))
+    }
+
+    fn note_table_size_change(&mut self, old: u32) {
+        let new = self.table.dynamic().size_soft_limit();
+        if old != new {
+            let smallest = self
+                .smallest_pending_table_size
+                .map_or(new, |size| cmp::min(size, new));
+            self.smallest_pending_table_size = Some(smallest);
+        }
+    }
 }
 
 /// Header Block Encoder.
+///
+/// The encoding of a header block is transactional.
+/// The changes of the state of the encoder made by this (e.g., insertions into the dynamic table)
+/// take effect only when `commit` (or `finish`) method is called.
+/// If this is dropped without committing or `abort` method is called,
+/// the state of the encoder is restored to the one before `Encoder::enter_header_block` was called.
+/// This is useful when the encoded block turns out not to be sent to the peer
+/// (e.g., it is too large for the peer or the stream has been reset).
+///
+/// Note that dropping this without calling `commit` or `finish` also rolls back the changes silently.
+///
+/// The restoration is made possible by recording the entries evicted from the dynamic table
+/// while encoding the block (the octets of them are copied),
+/// so the cost of it is proportional to the size of the changes rather than the size of the table.
+///
+/// # Examples
+///
+/// ```
+/// use hpack_codec::Encoder;
+///
+/// let mut encoder = Encoder::new(4096);
+///
+/// let mut header = encoder.enter_header_block(Vec::new()).unwrap();
+/// header.encode_header(b"foo", b"bar").unwrap();
+/// assert_eq!(header.table().dynamic().len(), 1);
+/// header.abort();
+/// assert!(encoder.table().dynamic().is_empty());
+///
+/// let mut header = encoder.enter_header_block(Vec::new()).unwrap();
+/// header.encode_header(b"foo", b"bar").unwrap();
+/// assert_eq!(header.block().len(), 9);
+/// header.commit();
+/// assert_eq!(encoder.table().dynamic().len(), 1);
+/// ```
 #[derive(Debug)]
 pub struct HeaderBlockEncoder<'a, W> {
-    table: &'a mut Table,
+    transaction: Transaction<'a>,
+    indexing_policy: &'a (dyn IndexingPolicy + Sync),
+    huffman_coding: HuffmanCoding,
     block: W,
 }
 impl<'a, W: Write> HeaderBlockEncoder<'a, W> {
@@ -75,22 +220,27 @@ impl<'a, W: Write> HeaderBlockEncoder<'a, W> {
         let field = field.into();
         match field {
             RawHeaderField::Indexed(ref field) => {
-                track!(self.table.validate_index(field.index()))?;
+                track!(self.transaction.table().validate_index(field.index()))?;
             }
             RawHeaderField::Literal(ref field) => {
                 if let FieldName::Index(index) = *field.name() {
-                    track!(self.table.validate_index(index))?;
+                    track!(self.transaction.table().validate_index(index))?;
                 };
                 if let LiteralFieldForm::WithIndexing = field.form() {
-                    let name = match *field.name() {
+                    let value = track!(field.value().to_plain_bytes())?;
+                    match *field.name() {
                         FieldName::Index(index) => {
-                            let entry = track!(self.table.get(index))?;
-                            entry.name().to_owned()
+                            let table = self.transaction.table_mut();
+                            track!(table.push_with_indexed_name(index, &value))?;
                         }
-                        FieldName::Name(ref name) => track!(name.to_plain_bytes())?.into_owned(),
-                    };
-                    let value = track!(field.value().to_plain_bytes())?.into_owned();
-                    self.table.dynamic_mut().push(name, value);
+                        FieldName::Name(ref name) => {
+                            let name = track!(name.to_plain_bytes())?;
+                            self.transaction
+                                .table_mut()
+                                .dynamic_mut()
+                                .push(&name, &value);
+                        }
+                    }
                 }
             }
         }
@@ -98,13 +248,371 @@ impl<'a, W: Write> HeaderBlockEncoder<'a, W> {
         Ok(())
     }
 
+    /// Encodes a header field, selecting the representation of it automatically.
+    ///
+    /// If the indexing table has an entry exactly matching the field,
+    /// it will be encoded as an indexed header field.
+    /// Otherwise, it will be encoded as a literal header field
+    /// (the name of the field is encoded as an index if the table contains it).
+    ///
+    /// The form of the literal is decided by the indexing policy of the encoder,
+    /// and the string literals are encoded in accordance with the huffman coding strategy of it.
+    /// If the policy returns `LiteralFieldForm::NeverIndexed`,
+    /// the field is always encoded as a literal.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hpack_codec::{Decoder, Encoder};
+    /// use hpack_codec::field::HeaderField;
+    ///
+    /// let mut encoder = Encoder::new(4096);
+    /// let mut header = encoder.enter_header_block(Vec::new()).unwrap();
+    /// header.encode_header(b":method", b"GET").unwrap(); // indexed (static table)
+    /// header.encode_header(b":path", b"/hello").unwrap(); // indexed name
+    /// header.encode_header(b"foo", b"bar").unwrap(); // literal
+    /// header.encode_header(b"foo", b"bar").unwrap(); // indexed (dynamic table)
+    /// let encoded_data = header.finish();
+    /// assert_eq!(encoded_data.len(), 19);
+    ///
+    /// let mut decoder = Decoder::new(4096);
+    /// let mut header = decoder.enter_header_block(&encoded_data[..]).unwrap();
+    /// assert_eq!(header.decode_field().unwrap(), HeaderField::new(b":method", b"GET").ok());
+    /// assert_eq!(header.decode_field().unwrap(), HeaderField::new(b":path", b"/hello").ok());
+    /// assert_eq!(header.decode_field().unwrap(), HeaderField::new(b"foo", b"bar").ok());
+    /// assert_eq!(header.decode_field().unwrap(), HeaderField::new(b"foo", b"bar").ok());
+    /// ```
+    pub fn encode_header(&mut self, name: &[u8], value: &[u8]) -> Result<()> {
+        let form = self.indexing_policy.literal_form(name, value);
+        track!(self.encode_header_with_form(name, value, form))
+    }
+
+    /// Encodes a header field in the same manner as `encode_header` method,
+    /// but uses `form` instead of consulting the indexing policy of the encoder.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hpack_codec::Encoder;
+    /// use hpack_codec::field::LiteralFieldForm;
+    ///
+    /// let mut encoder = Encoder::new(4096);
+    /// let mut header = encoder.enter_header_block(Vec::new()).unwrap();
+    /// header
+    ///     .encode_header_with_form(b":method", b"GET", LiteralFieldForm::NeverIndexed)
+    ///     .unwrap();
+    /// assert_eq!(header.finish(), b"\x12\x03GET");
+    /// ```
+    pub fn encode_header_with_form(
+        &mut self,
+        name: &[u8],
+        value: &[u8],
+        form: LiteralFieldForm,
+    ) -> Result<()> {
+        match self.transaction.table().find(name, value) {
+            Some((index, true)) if form != LiteralFieldForm::NeverIndexed => {
+                track!(self.encode_field(index))
+            }
+            Some((index, _)) => {
+                let field = LiteralHeaderField::with_indexed_name(index, value)
+                    .with_form(form)
+                    .with_huffman_coding(self.huffman_coding);
+                track!(self.encode_field(field))
+            }
+            None => {
+                let field = LiteralHeaderField::new(name, value)
+                    .with_form(form)
+                    .with_huffman_coding(self.huffman_coding);
+                track!(self.encode_field(field))
+            }
+        }
+    }
+
+    /// Encodes the header fields, selecting the representation of each field automatically.
+    ///
+    /// See the documentation of `encode_header` method for more details.
+    pub fn encode_headers<I, N, V>(&mut self, headers: I) -> Result<()>
+    where
+        I: IntoIterator<Item = (N, V)>,
+        N: AsRef<[u8]>,
+        V: AsRef<[u8]>,
+    {
+        for (name, value) in headers {
+            track!(self.encode_header(name.as_ref(), value.as_ref()))?;
+        }
+        Ok(())
+    }
+
     /// Finishes the encoding for the header block.
+    ///
+    /// This is equivalent to `commit` method.
     pub fn finish(self) -> W {
-        self.block
+        self.commit()
+    }
+
+    /// Finishes the encoding for the header block and commits the changes of the state of the encoder.
+    pub fn commit(self) -> W {
+        let HeaderBlockEncoder {
+            mut transaction,
+            block,
+            ..
+        } = self;
+        transaction.commit();
+        block
+    }
+
+    /// Aborts the encoding for the header block and restores the state of the encoder.
+    pub fn abort(self) {}
+
+    /// Returns the reference to the block being encoded.
+    pub fn block(&self) -> &W {
+        &self.block
     }
 
     /// Returns the indexing table of this decoder.
     pub fn table(&self) -> &Table {
-        &self.table
+        self.transaction.table()
+    }
+}
+
+#[derive(Debug)]
+struct Transaction<'a> {
+    table: &'a mut Table,
+    smallest_pending_table_size: &'a mut Option<u32>,
+    pending_table_size: Option<u32>,
+}
+impl<'a> Transaction<'a> {
+    fn table(&self) -> &Table {
+        self.table
+    }
+
+    fn table_mut(&mut self) -> &mut Table {
+        self.table
+    }
+
+    fn commit(&mut self) {
+        self.table.dynamic_mut().commit();
+        self.pending_table_size = None;
+    }
+}
+impl<'a> Drop for Transaction<'a> {
+    fn drop(&mut self) {
+        self.table.dynamic_mut().rollback();
+        if self.pending_table_size.is_some() {
+            *self.smallest_pending_table_size = self.pending_table_size;
+        }
+    }
+}
+
+/// Writer that splits written data into size-limited fragments.
+///
+/// At least one fragment (possibly empty) is always held by this writer.
+/// A new fragment is started only when the current one is full and
+/// more data is written.
+#[derive(Debug, Clone)]
+pub struct FragmentWriter {
+    first_fragment_size: usize,
+    fragment_size: usize,
+    fragments: Vec<Vec<u8>>,
+}
+impl FragmentWriter {
+    /// Makes a new `FragmentWriter` instance.
+    ///
+    /// # Errors
+    ///
+    /// If `fragment_size` is zero, an error will be returned.
+    pub fn new(first_fragment_size: usize, fragment_size: usize) -> Result<Self> {
+        track_assert_ne!(fragment_size, 0, ErrorKind::InvalidInput);
+        Ok(FragmentWriter {
+            first_fragment_size,
+            fragment_size,
+            fragments: vec![Vec::new()],
+        })
+    }
+
+    /// Returns the fragments written so far.
+    pub fn fragments(&self) -> &[Vec<u8>] {
+        &self.fragments
+    }
+
+    /// Converts into the written fragments.
+    pub fn into_fragments(self) -> Vec<Vec<u8>> {
+        self.fragments
+    }
+}
+impl Write for FragmentWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut written = 0;
+        while written < buf.len() {
+            let capacity = if self.fragments.len() == 1 {
+                self.first_fragment_size
+            } else {
+                self.fragment_size
+            };
+            let fragment = self.fragments.last_mut().expect("Never fails");
+            if fragment.len() == capacity {
+                self.fragments.push(Vec::new());
+                continue;
+            }
+            let size = cmp::min(capacity - fragment.len(), buf.len() - written);
+            fragment.extend_from_slice(&buf[written..written + size]);
+            written += size;
+        }
+        Ok(written)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Decoder, Transcoder};
+
+    #[test]
+    fn encoder_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Encoder>();
+        assert_send_sync::<HeaderBlockEncoder<Vec<u8>>>();
+        assert_send_sync::<Transcoder>();
+    }
+
+    #[test]
+    fn single_table_size_update() {
+        let mut encoder = Encoder::new(4096);
+        let mut decoder = Decoder::new(4096);
+        track_try_unwrap!(encoder.set_dynamic_table_size_soft_limit(1024));
+
+        let mut block = track_try_unwrap!(encoder.enter_header_block(Vec::new()));
+        track_try_unwrap!(block.encode_header(b"foo", b"bar"));
+        let data = block.finish();
+        assert_eq!(&data[..3], [0x3f, 0xe1, 0x07]);
+
+        track_try_unwrap!(decoder.enter_header_block(&data[..]));
+        assert_eq!(decoder.table().dynamic().size_soft_limit(), 1024);
+
+        // No updates are needed for the subsequent blocks
+        let block = track_try_unwrap!(encoder.enter_header_block(Vec::new()));
+        assert!(block.finish().is_empty());
+    }
+
+    #[test]
+    fn smallest_and_final_table_size_updates() {
+        let mut encoder = Encoder::new(4096);
+        let mut decoder = Decoder::new(4096);
+        track_try_unwrap!(encoder.set_dynamic_table_size_soft_limit(1024));
+        track_try_unwrap!(encoder.set_dynamic_table_size_soft_limit(0));
+        track_try_unwrap!(encoder.set_dynamic_table_size_soft_limit(2048));
+
+        let block = track_try_unwrap!(encoder.enter_header_block(Vec::new()));
+        let data = block.finish();
+        assert_eq!(data, [0x20, 0x3f, 0xe1, 0x0f]);
+
+        track_try_unwrap!(decoder.enter_header_block(&data[..]));
+        assert_eq!(decoder.table().dynamic().size_soft_limit(), 2048);
+    }
+
+    #[test]
+    fn table_size_updates_caused_by_hard_limit() {
+        let mut encoder = Encoder::new(4096);
+        let mut decoder = Decoder::new(4096);
+
+        {
+            let mut block = track_try_unwrap!(encoder.enter_header_block(Vec::new()));
+            track_try_unwrap!(block.encode_header(b"foo", b"bar"));
+            let data = block.finish();
+            let mut block = track_try_unwrap!(decoder.enter_header_block(&data[..]));
+            track_try_unwrap!(block.decode_field());
+        }
+        assert_eq!(decoder.table().dynamic().entries().len(), 1);
+
+        encoder.set_dynamic_table_size_hard_limit(8192); // The soft limit is unchanged
+        encoder.set_dynamic_table_size_hard_limit(0);
+        encoder.set_dynamic_table_size_hard_limit(4096); // The soft limit is unchanged
+        track_try_unwrap!(encoder.set_dynamic_table_size_soft_limit(100));
+
+        let mut block = track_try_unwrap!(encoder.enter_header_block(Vec::new()));
+        track_try_unwrap!(block.encode_header(b"foo", b"bar"));
+        let data = block.finish();
+        assert_eq!(&data[..3], [0x20, 0x3f, 0x45]);
+
+        let mut block = track_try_unwrap!(decoder.enter_header_block(&data[..]));
+        assert_eq!(block.table().dynamic().entries().len(), 0);
+        track_try_unwrap!(block.decode_field());
+        assert_eq!(block.table().dynamic().entries().len(), 1);
+        assert_eq!(decoder.table().dynamic().size_soft_limit(), 100);
+    }
+
+    #[test]
+    fn fragmented_header_block() {
+        let headers = [
+            (&b":method"[..], &b"GET"[..]),
+            (b":path", b"/index.html"),
+            (b"custom-key", b"custom-value"),
+            (b"cookie", &[b'a'; 100][..]),
+        ];
+        let mut encoder = Encoder::new(4096);
+        let mut block = track_try_unwrap!(encoder.enter_header_block(Vec::new()));
+        track_try_unwrap!(block.encode_headers(headers.iter().cloned()));
+        let data = block.finish();
+
+        for &(first_fragment_size, fragment_size) in &[(0, 1), (3, 7), (16, 16), (1000, 1)] {
+            let mut encoder = Encoder::new(4096);
+            let mut block = track_try_unwrap!(
+                encoder.enter_fragmented_header_block(first_fragment_size, fragment_size)
+            );
+            track_try_unwrap!(block.encode_headers(headers.iter().cloned()));
+            let fragments = block.finish().into_fragments();
+
+            assert!(fragments[0].len() <= first_fragment_size);
+            assert!(fragments[1..]
+                .iter()
+                .all(|f| !f.is_empty() && f.len() <= fragment_size));
+            assert_eq!(fragments.concat(), data);
+        }
+
+        // Empty block
+        let block = track_try_unwrap!(encoder.enter_fragmented_header_block(16, 16));
+        assert_eq!(block.finish().into_fragments(), [Vec::<u8>::new()]);
+
+        assert!(encoder.enter_fragmented_header_block(16, 0).is_err());
+    }
+
+    #[test]
+    fn transactional_header_block() {
+        let mut encoder = Encoder::new(100);
+        let mut block = track_try_unwrap!(encoder.enter_header_block(Vec::new()));
+        track_try_unwrap!(block.encode_header(b"foo", b"bar"));
+        block.commit();
+
+        // Aborted by dropping
+        track_try_unwrap!(encoder.set_dynamic_table_size_soft_limit(50));
+        {
+            let mut block = track_try_unwrap!(encoder.enter_header_block(Vec::new()));
+            track_try_unwrap!(block.encode_header(b"baz", b"qux"));
+            assert_eq!(block.block()[..2], [0x3f, 0x13]);
+            assert_eq!(block.table().dynamic().entry(0).unwrap().name(), b"baz");
+            assert_eq!(block.table().dynamic().len(), 1);
+        }
+        assert_eq!(encoder.table().dynamic().len(), 1);
+        assert_eq!(encoder.table().dynamic().entry(0).unwrap().name(), b"foo");
+
+        // Aborted explicitly
+        let mut block = track_try_unwrap!(encoder.enter_header_block(Vec::new()));
+        track_try_unwrap!(block.encode_header(b"foo", b"bar"));
+        assert_eq!(block.block()[..], [0x3f, 0x13, 0xbe]);
+        block.abort();
+
+        // The size update is signaled again
+        let mut block = track_try_unwrap!(encoder.enter_header_block(Vec::new()));
+        track_try_unwrap!(block.encode_header(b"baz", b"qux"));
+        let data = block.commit();
+        assert_eq!(data[..2], [0x3f, 0x13]);
+        assert_eq!(encoder.table().dynamic().len(), 1);
+        assert_eq!(encoder.table().dynamic().entry(0).unwrap().name(), b"baz");
+
+        let block = track_try_unwrap!(encoder.enter_header_block(Vec::new()));
+        assert!(block.finish().is_empty());
     }
 }
@@ -0,0 +1,95 @@
+use std::io;
+use trackable::error::{ErrorKind as TrackableErrorKind, ErrorKindExt, TrackableError};
+
+/// This crate specific `Error` type.
+#[derive(Debug, Clone, TrackableError)]
+pub struct Error(TrackableError<ErrorKind>);
+impl From<io::Error> for Error {
+    fn from(f: io::Error) -> Self {
+        if f.kind() == io::ErrorKind::UnexpectedEof {
+            ErrorKind::TruncatedInput.cause(f).into()
+        } else {
+            ErrorKind::Io.cause(f).into()
+        }
+    }
+}
+
+/// Possible error kinds.
+///
+/// The kinds except `HeaderListTooLarge`, `LimitExceeded`, `Io` and `InvalidInput`
+/// indicate that the peer violated HPACK,
+/// and they should be treated as connection errors of type `COMPRESSION_ERROR` in HTTP/2.
+///
+/// `HeaderListTooLarge` and `LimitExceeded` indicate that a local resource limit is exceeded,
+/// and `Io` and `InvalidInput` are not related to the peer.
+/// See the documentation of each kind for how it maps to HTTP/2.
+///
+/// See: [3.  Header Field Representation Processing](https://tools.ietf.org/html/rfc7541#section-3)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// An index is zero or exceeds the number of the entries in the indexing table.
+    InvalidIndex,
+
+    /// An integer is greater than the acceptable maximum value.
+    IntegerOverflow,
+
+    /// An integer has more continuation octets than needed for the acceptable maximum value.
+    OverlongInteger,
+
+    /// An integer is encoded with redundant continuation octets (in the strict mode).
+    NonMinimalInteger,
+
+    /// A huffman encoded string contains an invalid code (i.e., the EOS symbol).
+    InvalidHuffmanCode,
+
+    /// The padding of a huffman encoded string does not correspond to
+    /// the most significant bits of the EOS symbol.
+    InvalidPadding,
+
+    /// The padding of a huffman encoded string is longer than 7 bits.
+    PaddingTooLong,
+
+    /// A dynamic table size update occurs at a position other than the beginning of a header block.
+    UnexpectedSizeUpdate,
+
+    /// A header block does not begin with the required dynamic table size update.
+    MissingSizeUpdate,
+
+    /// A dynamic table size update exceeds the acceptable maximum size.
+    SizeUpdateTooLarge,
+
+    /// The input ended in the middle of a representation.
+    TruncatedInput,
+
+    /// The decoded header list exceeds the acceptable maximum size.
+    ///
+    /// This is not a protocol violation.
+    /// If the decoder drains oversized header lists, the indexing table remains in sync,
+    /// so this can be handled as a stream error in HTTP/2
+    /// (e.g., responding with `431 Request Header Fields Too Large` or resetting the stream).
+    /// Otherwise, the remaining part of the block has not been processed,
+    /// so this must be treated as a connection error of type `COMPRESSION_ERROR`.
+    ///
+    /// See: [10.5.1.  Limits on Header Block Size](https://tools.ietf.org/html/rfc7540#section-10.5.1)
+    HeaderListTooLarge,
+
+    /// A resource limit of the decoder is exceeded (e.g., the number of header fields in a block).
+    ///
+    /// This is not a protocol violation, but the decoding stops in the middle of the block
+    /// and the indexing table may be out of sync with the peer.
+    /// So this must be treated as a connection error in HTTP/2
+    /// (e.g., of type `ENHANCE_YOUR_CALM` or `COMPRESSION_ERROR`).
+    LimitExceeded,
+
+    /// An I/O error occurred (e.g., writing an encoded header block failed).
+    ///
+    /// This is a local error, and no HTTP/2 error code corresponds to it.
+    /// If it occurs while encoding, the encoded block must not be sent to the peer.
+    Io,
+
+    /// Invalid input was given by the caller.
+    ///
+    /// This indicates a bug of the caller, and no HTTP/2 error code corresponds to it.
+    InvalidInput,
+}
+impl TrackableErrorKind for ErrorKind {}
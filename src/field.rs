@@ -1,23 +1,28 @@
 //! Header Field.
-use crate::huffman;
 use crate::io::SliceReader;
-use crate::literal::{self, HpackString};
+use crate::literal::{self, HpackString, HuffmanCoding};
 use crate::table::{Index, StaticEntry};
-use crate::Result;
+use crate::{ErrorKind, Result};
 use byteorder::WriteBytesExt;
 use std;
 use std::borrow::Cow;
+use std::fmt;
 use std::io::Write;
-use trackable::error::Failed;
+use std::ops::Range;
+use std::sync::Arc;
 
 /// Header Field.
 ///
 /// This is a name-value pair. Both the name and value are
 /// treated as opaque sequences of octets.
+///
+/// The fields derived from the dynamic table hold reference-counted copies of the octets
+/// of the table entries, so they can be converted into owned fields (`into_owned`)
+/// and outlive the eviction of the entries without copying again.
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct HeaderField<'a> {
-    name: Cow<'a, [u8]>,
-    value: Cow<'a, [u8]>,
+    name: Octets<'a>,
+    value: Octets<'a>,
 }
 impl<'a> HeaderField<'a> {
     /// Makes a new `HeaderField` instance.
@@ -26,18 +31,18 @@ impl<'a> HeaderField<'a> {
     ///
     /// If the size of resulting header is too large, the function will returns an `Error`.
     ///
-    /// The maximum size of a header (i.e., the sum of it's name and value) is `std::u16::MAX - 32`.
+    /// The maximum size of a header (i.e., the sum of it's name and value) is `std::u32::MAX - 32`.
     pub fn new(name: &'a [u8], value: &'a [u8]) -> Result<Self> {
         let entry_size = name.len() + value.len() + 32;
         track_assert!(
-            entry_size <= std::u16::MAX as usize,
-            Failed,
+            entry_size <= u32::MAX as usize,
+            ErrorKind::InvalidInput,
             "Too large header field: {}",
             entry_size
         );
         Ok(HeaderField {
-            name: Cow::Borrowed(name),
-            value: Cow::Borrowed(value),
+            name: Octets::Borrowed(name),
+            value: Octets::Borrowed(value),
         })
     }
 
@@ -54,25 +59,106 @@ impl<'a> HeaderField<'a> {
     /// Returns the entry size of this header field.
     ///
     /// See: [4.1.  Calculating Table Size](https://tools.ietf.org/html/rfc7541#section-4.1)
-    pub fn entry_size(&self) -> u16 {
-        (self.name.len() + self.value.len() + 32) as u16
+    pub fn entry_size(&self) -> u32 {
+        (self.name().len() + self.value().len() + 32) as u32
+    }
+
+    /// Converts into the owned `HeaderField` that does not borrow anything.
+    ///
+    /// The octets derived from the dynamic table and the ones of the static table are not copied.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hpack_codec::field::HeaderField;
+    ///
+    /// let field = {
+    ///     let name = b"foo".to_vec();
+    ///     HeaderField::new(&name, b"bar").unwrap().into_owned()
+    /// };
+    /// assert_eq!(field.name(), b"foo");
+    /// ```
+    pub fn into_owned(self) -> HeaderField<'static> {
+        HeaderField {
+            name: self.name.into_owned(),
+            value: self.value.into_owned(),
+        }
     }
 
     pub(crate) fn from_cow(name: Cow<'a, [u8]>, value: Cow<'a, [u8]>) -> Self {
         let entry_size = name.len() + value.len() + 32;
-        debug_assert!(entry_size <= std::u16::MAX as usize);
-        HeaderField { name, value }
+        debug_assert!(entry_size <= u32::MAX as usize);
+        HeaderField {
+            name: Octets::from(name),
+            value: Octets::from(value),
+        }
     }
-    pub(crate) fn into_cow_name(self) -> Cow<'a, [u8]> {
-        self.name
+    pub(crate) fn from_static(name: &'static [u8], value: &'static [u8]) -> Self {
+        HeaderField {
+            name: Octets::Static(name),
+            value: Octets::Static(value),
+        }
     }
-    pub(crate) fn as_borrowed(&self) -> HeaderField {
+    pub(crate) fn from_octets(name: Octets<'a>, value: Octets<'a>) -> Self {
+        let entry_size = name.as_ref().len() + value.as_ref().len() + 32;
+        debug_assert!(entry_size <= u32::MAX as usize);
+        HeaderField { name, value }
+    }
+    pub(crate) fn with_value(self, value: Cow<'a, [u8]>) -> Self {
+        let entry_size = self.name().len() + value.len() + 32;
+        debug_assert!(entry_size <= u32::MAX as usize);
         HeaderField {
-            name: Cow::Borrowed(self.name.as_ref()),
-            value: Cow::Borrowed(self.value.as_ref()),
+            name: self.name,
+            value: Octets::from(value),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub(crate) enum Octets<'a> {
+    Borrowed(&'a [u8]),
+    Static(&'static [u8]),
+    Owned(Vec<u8>),
+    Shared(Arc<[u8]>, Range<usize>),
+}
+impl<'a> Octets<'a> {
+    fn into_owned(self) -> Octets<'static> {
+        match self {
+            Octets::Borrowed(x) => Octets::Owned(x.to_owned()),
+            Octets::Static(x) => Octets::Static(x),
+            Octets::Owned(x) => Octets::Owned(x),
+            Octets::Shared(x, range) => Octets::Shared(x, range),
+        }
+    }
+}
+impl<'a> fmt::Debug for Octets<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.as_ref().fmt(f)
+    }
+}
+impl<'a> AsRef<[u8]> for Octets<'a> {
+    fn as_ref(&self) -> &[u8] {
+        match *self {
+            Octets::Borrowed(x) | Octets::Static(x) => x,
+            Octets::Owned(ref x) => x,
+            Octets::Shared(ref x, ref range) => &x[range.clone()],
         }
     }
 }
+impl<'a> From<Cow<'a, [u8]>> for Octets<'a> {
+    fn from(f: Cow<'a, [u8]>) -> Self {
+        match f {
+            Cow::Borrowed(x) => Octets::Borrowed(x),
+            Cow::Owned(x) => Octets::Owned(x),
+        }
+    }
+}
+impl<'a> PartialEq for Octets<'a> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_ref() == other.as_ref()
+    }
+}
+impl<'a> Eq for Octets<'a> {}
 
 /// Raw representation of a header field.
 ///
@@ -96,7 +182,7 @@ impl<'a> RawHeaderField<'a> {
             track!(IndexedHeaderField::decode(reader)).map(RawHeaderField::Indexed)
         } else if octet >> 5 == 0b001 {
             track_panic!(
-                Failed,
+                ErrorKind::UnexpectedSizeUpdate,
                 "Dynamic table size update MUST occur at the beginning of the first header block"
             );
         } else {
@@ -137,10 +223,10 @@ impl IndexedHeaderField {
     }
 
     fn encode<W: Write>(&self, writer: W) -> Result<()> {
-        track!(literal::encode_u16(writer, 1, 7, self.0.as_u16()))
+        track!(literal::encode_u32(writer, 1, 7, self.0.as_u32()))
     }
     fn decode(reader: &mut SliceReader) -> Result<Self> {
-        let index = track!(literal::decode_u16(reader, 7))?.1;
+        let index = track!(reader.read_integer(7))?.1;
         let index = track!(Index::new(index))?;
         Ok(IndexedHeaderField(index))
     }
@@ -154,6 +240,8 @@ pub struct LiteralHeaderField<'a> {
     name: FieldName<'a>,
     value: HpackString<'a>,
     form: LiteralFieldForm,
+    name_huffman_coding: HuffmanCoding,
+    value_huffman_coding: HuffmanCoding,
 }
 impl<'a> LiteralHeaderField<'a> {
     /// Makes a new `LiteralHeaderField` instance.
@@ -171,6 +259,8 @@ impl<'a> LiteralHeaderField<'a> {
             name: FieldName::Name(HpackString::Plain(Cow::Borrowed(name))),
             value: HpackString::Plain(Cow::Borrowed(value)),
             form: LiteralFieldForm::WithoutIndexing,
+            name_huffman_coding: HuffmanCoding::Never,
+            value_huffman_coding: HuffmanCoding::Never,
         }
     }
 
@@ -196,9 +286,17 @@ impl<'a> LiteralHeaderField<'a> {
             name: FieldName::Index(name.into()),
             value: HpackString::Plain(Cow::Borrowed(value)),
             form: LiteralFieldForm::WithoutIndexing,
+            name_huffman_coding: HuffmanCoding::Never,
+            value_huffman_coding: HuffmanCoding::Never,
         }
     }
 
+    /// Specifies the form of this header field.
+    pub fn with_form(mut self, form: LiteralFieldForm) -> Self {
+        self.form = form;
+        self
+    }
+
     /// Specifies to index this header field into a dynamic table.
     pub fn with_indexing(mut self) -> Self {
         self.form = LiteralFieldForm::WithIndexing;
@@ -212,30 +310,56 @@ impl<'a> LiteralHeaderField<'a> {
     }
 
     /// Encodes the name of this header field by huffman coding.
+    ///
+    /// The huffman coding is applied when this field is encoded.
     pub fn with_huffman_encoded_name(mut self) -> Self {
-        if let FieldName::Name(HpackString::Plain(name)) = self.name {
-            self.name = FieldName::Name(HpackString::Huffman(Cow::Owned(huffman::encode(&name))));
-            self
-        } else {
-            self
-        }
+        self.name_huffman_coding = HuffmanCoding::Always;
+        self
     }
 
     /// Encodes the value of this header field by huffman coding.
+    ///
+    /// The huffman coding is applied when this field is encoded.
     pub fn with_huffman_encoded_value(mut self) -> Self {
-        if let HpackString::Plain(value) = self.value {
-            self.value = HpackString::Huffman(Cow::Owned(huffman::encode(&value)));
-        }
+        self.value_huffman_coding = HuffmanCoding::Always;
+        self
+    }
+
+    /// Applies huffman coding to the name and value of this header field in accordance with `coding`.
+    ///
+    /// The huffman coding is applied when this field is encoded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hpack_codec::Encoder;
+    /// use hpack_codec::field::LiteralHeaderField;
+    /// use hpack_codec::literal::HuffmanCoding;
+    ///
+    /// let field = LiteralHeaderField::new(b"custom-key", b"{~^}|\\<>")
+    ///     .with_huffman_coding(HuffmanCoding::Auto);
+    ///
+    /// let mut encoder = Encoder::new(4096);
+    /// let mut header = encoder.enter_header_block(Vec::new()).unwrap();
+    /// header.encode_field(field).unwrap();
+    /// assert_eq!(
+    ///     header.finish(),
+    ///     b"\x00\x88\x25\xa8\x49\xe9\x5b\xa9\x7d\x7f\x08{~^}|\\<>"
+    /// );
+    /// ```
+    pub fn with_huffman_coding(mut self, coding: HuffmanCoding) -> Self {
+        self.name_huffman_coding = coding;
+        self.value_huffman_coding = coding;
         self
     }
 
     /// Returns the name of this header field.
-    pub fn name(&self) -> &FieldName {
+    pub fn name(&self) -> &FieldName<'_> {
         &self.name
     }
 
     /// Returns the value of this header field.
-    pub fn value(&self) -> &HpackString {
+    pub fn value(&self) -> &HpackString<'_> {
         &self.value
     }
 
@@ -244,22 +368,44 @@ impl<'a> LiteralHeaderField<'a> {
         self.form
     }
 
+    /// Returns the huffman coding strategy applied to the name of this header field.
+    ///
+    /// Note that this is not applied to the name that is already huffman encoded.
+    pub fn name_huffman_coding(&self) -> HuffmanCoding {
+        self.name_huffman_coding
+    }
+
+    /// Returns the huffman coding strategy applied to the value of this header field.
+    ///
+    /// Note that this is not applied to the value that is already huffman encoded.
+    pub fn value_huffman_coding(&self) -> HuffmanCoding {
+        self.value_huffman_coding
+    }
+
     pub(crate) fn unwrap(self) -> (FieldName<'a>, HpackString<'a>, LiteralFieldForm) {
         (self.name, self.value, self.form)
     }
     fn encode<W: Write>(&self, mut writer: W) -> Result<()> {
         track!(self.encode_name(&mut writer))?;
-        track!(self.value.encode(writer))
+        track!(self
+            .value
+            .encode_with_huffman_coding(writer, self.value_huffman_coding))
     }
 
     fn decode(reader: &mut SliceReader<'a>, first_octet: u8) -> Result<Self> {
         let (name, form) = track!(Self::decode_name_and_form(reader, first_octet))?;
         let value = track!(HpackString::decode(reader))?;
-        Ok(LiteralHeaderField { name, value, form })
+        Ok(LiteralHeaderField {
+            name,
+            value,
+            form,
+            name_huffman_coding: HuffmanCoding::Never,
+            value_huffman_coding: HuffmanCoding::Never,
+        })
     }
 
     fn decode_name_and_form(
-        mut reader: &mut SliceReader<'a>,
+        reader: &mut SliceReader<'a>,
         first_octet: u8,
     ) -> Result<(FieldName<'a>, LiteralFieldForm)> {
         if first_octet >> 6 == 0b01 {
@@ -268,18 +414,18 @@ impl<'a> LiteralHeaderField<'a> {
                 let name = track!(HpackString::decode(reader))?;
                 FieldName::Name(name)
             } else {
-                let index = track!(literal::decode_u16(&mut reader, 6))?.1;
+                let index = track!(reader.read_integer(6))?.1;
                 let index = track!(Index::new(index))?;
                 FieldName::Index(index)
             };
             Ok((name, LiteralFieldForm::WithIndexing))
-        } else if first_octet == 0b0001_0000 {
+        } else if first_octet >> 4 == 0b0001 {
             let name = if first_octet & 0b1111 == 0 {
                 reader.consume(1);
                 let name = track!(HpackString::decode(reader))?;
                 FieldName::Name(name)
             } else {
-                let index = track!(literal::decode_u16(&mut reader, 4))?.1;
+                let index = track!(reader.read_integer(4))?.1;
                 let index = track!(Index::new(index))?;
                 FieldName::Index(index)
             };
@@ -290,7 +436,7 @@ impl<'a> LiteralHeaderField<'a> {
                 let name = track!(HpackString::decode(reader))?;
                 FieldName::Name(name)
             } else {
-                let index = track!(literal::decode_u16(&mut reader, 4))?.1;
+                let index = track!(reader.read_integer(4))?.1;
                 let index = track!(Index::new(index))?;
                 FieldName::Index(index)
             };
@@ -303,25 +449,25 @@ impl<'a> LiteralHeaderField<'a> {
         use self::LiteralFieldForm::*;
         match (self.form, &self.name) {
             (WithIndexing, &Index(index)) => {
-                track!(literal::encode_u16(writer, 0b01, 6, index.as_u16()))
+                track!(literal::encode_u32(writer, 0b01, 6, index.as_u32()))
             }
-            (WithIndexing, &Name(ref name)) => {
+            (WithIndexing, Name(name)) => {
                 track_io!(writer.write_u8(0b01_000000))?;
-                track!(name.encode(writer))
+                track!(name.encode_with_huffman_coding(writer, self.name_huffman_coding))
             }
             (WithoutIndexing, &Index(index)) => {
-                track!(literal::encode_u16(writer, 0b0000, 4, index.as_u16()))
+                track!(literal::encode_u32(writer, 0b0000, 4, index.as_u32()))
             }
-            (WithoutIndexing, &Name(ref name)) => {
+            (WithoutIndexing, Name(name)) => {
                 track_io!(writer.write_u8(0b0000_0000))?;
-                track!(name.encode(writer))
+                track!(name.encode_with_huffman_coding(writer, self.name_huffman_coding))
             }
             (NeverIndexed, &Index(index)) => {
-                track!(literal::encode_u16(writer, 0b0001, 4, index.as_u16()))
+                track!(literal::encode_u32(writer, 0b0001, 4, index.as_u32()))
             }
-            (NeverIndexed, &Name(ref name)) => {
+            (NeverIndexed, Name(name)) => {
                 track_io!(writer.write_u8(0b0001_0000))?;
-                track!(name.encode(writer))
+                track!(name.encode_with_huffman_coding(writer, self.name_huffman_coding))
             }
         }
     }
@@ -349,3 +495,218 @@ pub enum FieldName<'a> {
     Index(Index),
     Name(HpackString<'a>),
 }
+
+/// Information about how a decoded header field was represented in the header block.
+///
+/// This is returned by `HeaderBlockDecoder::decode_field_with_info` method and
+/// may be useful for intermediaries that re-encode header fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldInfo {
+    representation: FieldRepresentation,
+    index: Option<Index>,
+    name_source: NameSource,
+    name_huffman_encoded: bool,
+    value_huffman_encoded: bool,
+}
+impl FieldInfo {
+    /// Returns the representation of the field.
+    pub fn representation(&self) -> FieldRepresentation {
+        self.representation
+    }
+
+    /// Returns the absolute index used by the representation of the field.
+    ///
+    /// This is the index of the whole field if it is an indexed header field,
+    /// or the index of the name if it is a literal header field with an indexed name.
+    /// Note that the index refers to the indexing table before the field is decoded.
+    pub fn index(&self) -> Option<Index> {
+        self.index
+    }
+
+    /// Returns where the name of the field came from.
+    pub fn name_source(&self) -> NameSource {
+        self.name_source
+    }
+
+    /// Returns `true` if the name of the field was huffman encoded, otherwise `false`.
+    pub fn is_name_huffman_encoded(&self) -> bool {
+        self.name_huffman_encoded
+    }
+
+    /// Returns `true` if the value of the field was huffman encoded, otherwise `false`.
+    pub fn is_value_huffman_encoded(&self) -> bool {
+        self.value_huffman_encoded
+    }
+
+    pub(crate) fn new(field: &RawHeaderField) -> Self {
+        match *field {
+            RawHeaderField::Indexed(ref field) => FieldInfo {
+                representation: FieldRepresentation::Indexed,
+                index: Some(field.index()),
+                name_source: NameSource::from_index(field.index()),
+                name_huffman_encoded: false,
+                value_huffman_encoded: false,
+            },
+            RawHeaderField::Literal(ref field) => {
+                let (index, name_source, name_huffman_encoded) = match *field.name() {
+                    FieldName::Index(index) => (Some(index), NameSource::from_index(index), false),
+                    FieldName::Name(ref name) => {
+                        (None, NameSource::Literal, name.is_huffman_encoded())
+                    }
+                };
+                FieldInfo {
+                    representation: FieldRepresentation::Literal(field.form()),
+                    index,
+                    name_source,
+                    name_huffman_encoded,
+                    value_huffman_encoded: field.value().is_huffman_encoded(),
+                }
+            }
+        }
+    }
+}
+
+/// Representations of header fields.
+///
+/// See: [6.  Binary Format](https://tools.ietf.org/html/rfc7541#section-6)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FieldRepresentation {
+    /// See: [6.1.  Indexed Header Field Representation](https://tools.ietf.org/html/rfc7541#section-6.1)
+    Indexed,
+
+    /// See: [6.2.  Literal Header Field Representation](https://tools.ietf.org/html/rfc7541#section-6.2)
+    Literal(LiteralFieldForm),
+}
+
+/// Sources of the names of decoded header fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NameSource {
+    /// The name was represented as a string literal.
+    Literal,
+
+    /// The name was taken from the static table.
+    StaticTable,
+
+    /// The name was taken from the dynamic table.
+    DynamicTable,
+}
+impl NameSource {
+    fn from_index(index: Index) -> Self {
+        if StaticEntry::from_index(index).is_some() {
+            NameSource::StaticTable
+        } else {
+            NameSource::DynamicTable
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Decoder, Encoder};
+
+    #[test]
+    fn literal_header_field_roundtrip() {
+        let forms = [
+            LiteralFieldForm::WithIndexing,
+            LiteralFieldForm::WithoutIndexing,
+            LiteralFieldForm::NeverIndexed,
+        ];
+        let indices = [
+            Index::from(StaticEntry::Authority),
+            Index::from(StaticEntry::WwwAuthenticate),
+            Index::dynamic_table_offset(),
+        ];
+        for &form in &forms {
+            for &huffman in &[false, true] {
+                let mut encoder = Encoder::new(4096);
+                let mut block = track_try_unwrap!(encoder.enter_header_block(Vec::new()));
+                track_try_unwrap!(
+                    block.encode_field(LiteralHeaderField::new(b"foo", b"bar").with_indexing())
+                );
+                for &index in &indices {
+                    let mut field =
+                        LiteralHeaderField::with_indexed_name(index, b"baz").with_form(form);
+                    if huffman {
+                        field = field.with_huffman_encoded_value();
+                    }
+                    track_try_unwrap!(block.encode_field(field));
+                }
+                let mut field = LiteralHeaderField::new(b"qux", b"quux").with_form(form);
+                if huffman {
+                    field = field
+                        .with_huffman_encoded_name()
+                        .with_huffman_encoded_value();
+                }
+                track_try_unwrap!(block.encode_field(field));
+                let data = block.finish();
+
+                let mut decoder = Decoder::new(4096);
+                let mut block = track_try_unwrap!(decoder.enter_header_block(&data[..]));
+                track_try_unwrap!(block.decode_field());
+                for &index in &indices {
+                    let field = track_try_unwrap!(block.decode_raw_field()).unwrap();
+                    if let RawHeaderField::Literal(field) = field {
+                        assert_eq!(field.form(), form);
+                        assert_eq!(field.value().is_huffman_encoded(), huffman);
+                        if let FieldName::Index(i) = *field.name() {
+                            assert_eq!(i, index);
+                        } else {
+                            panic!("Unexpected name: {:?}", field.name());
+                        }
+                        let value = track_try_unwrap!(field.value().to_plain_bytes());
+                        assert_eq!(value.as_ref(), b"baz");
+                    } else {
+                        panic!("Unexpected field: {:?}", field);
+                    }
+                }
+                let field = track_try_unwrap!(block.decode_raw_field()).unwrap();
+                if let RawHeaderField::Literal(field) = field {
+                    assert_eq!(field.form(), form);
+                    if let FieldName::Name(ref name) = *field.name() {
+                        assert_eq!(name.is_huffman_encoded(), huffman);
+                        let name = track_try_unwrap!(name.to_plain_bytes());
+                        assert_eq!(name.as_ref(), b"qux");
+                    } else {
+                        panic!("Unexpected name: {:?}", field.name());
+                    }
+                    let value = track_try_unwrap!(field.value().to_plain_bytes());
+                    assert_eq!(value.as_ref(), b"quux");
+                } else {
+                    panic!("Unexpected field: {:?}", field);
+                }
+                assert!(track_try_unwrap!(block.decode_raw_field()).is_none());
+            }
+        }
+    }
+
+    #[test]
+    fn never_indexed_field_with_indexed_name() {
+        let data = [0x1f, 0x11, 0x03, b'f', b'o', b'o'];
+        let mut decoder = Decoder::new(4096);
+        let mut block = track_try_unwrap!(decoder.enter_header_block(&data[..]));
+        let field = track_try_unwrap!(block.decode_raw_field()).unwrap();
+        if let RawHeaderField::Literal(field) = field {
+            assert_eq!(field.form(), LiteralFieldForm::NeverIndexed);
+            if let FieldName::Index(index) = *field.name() {
+                assert_eq!(index, Index::from(StaticEntry::Cookie));
+            } else {
+                panic!("Unexpected name: {:?}", field.name());
+            }
+        } else {
+            panic!("Unexpected field: {:?}", field);
+        }
+    }
+
+    #[test]
+    fn debug_format() {
+        let mut decoder = Decoder::new(4096);
+        let data = [0x40, 0x03, b'f', b'o', b'o', 0x03, b'b', b'a', b'r'];
+        let mut block = track_try_unwrap!(decoder.enter_header_block(&data[..]));
+        let field = track_try_unwrap!(block.decode_field()).unwrap();
+        assert_eq!(
+            format!("{:?}", field),
+            "HeaderField { name: [102, 111, 111], value: [98, 97, 114] }"
+        );
+    }
+}
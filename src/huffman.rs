@@ -1,100 +1,254 @@
-use crate::Result;
+//! Huffman coding for string literals.
+//!
+//! See: [Appendix B.  Huffman Code](https://tools.ietf.org/html/rfc7541#appendix-B)
+//!
+//! # Examples
+//!
+//! ```
+//! use hpack_codec::huffman::{self, HuffmanDecoder};
+//!
+//! let encoded = huffman::encode(b"www.example.com");
+//! assert_eq!(encoded.len(), huffman::encoded_len(b"www.example.com"));
+//! assert_eq!(huffman::decode(&encoded).unwrap(), b"www.example.com");
+//!
+//! // Streaming decoding
+//! let mut decoder = HuffmanDecoder::new();
+//! let mut decoded = Vec::new();
+//! for chunk in encoded.chunks(3) {
+//!     decoder.decode(chunk, &mut decoded).unwrap();
+//! }
+//! decoder.finish().unwrap();
+//! assert_eq!(decoded, b"www.example.com");
+//! ```
+#![allow(clippy::unusual_byte_groupings)]
+use crate::{ErrorKind, Result};
 use std::cmp;
-use trackable::error::Failed;
+use std::io::Write;
+use std::sync::OnceLock;
 
-#[derive(Debug)]
-pub struct BitReader<'a> {
-    data: &'a [u8],
-    offset: usize,
+/// Decodes the huffman encoded octets.
+///
+/// # Errors
+///
+/// If `data` contains the EOS symbol or the padding of it is invalid, an error will be returned.
+pub fn decode(data: &[u8]) -> Result<Vec<u8>> {
+    track!(decode_with_limit(data, usize::MAX))
 }
-impl<'a> BitReader<'a> {
-    pub fn new(data: &'a [u8]) -> Self {
-        BitReader { data, offset: 0 }
-    }
-    pub fn remaining_bits(&self) -> usize {
-        self.data.len() * 8 - self.offset
-    }
-    pub fn read_bits(&mut self, bits: usize) -> Result<u32> {
-        debug_assert!(1 <= bits && bits <= 5);
-        debug_assert!(self.offset + bits <= self.data.len() * 8);
-        let new_offset = self.offset + bits;
 
-        let mut value = 0;
-        for i in self.offset..new_offset {
-            let bit = (self.data[i / 8] >> (7 - (i % 8))) & 0b1;
-            value = (value << 1) | bit as u32;
+pub(crate) fn decode_with_limit(data: &[u8], max_len: usize) -> Result<Vec<u8>> {
+    let mut buf = Vec::with_capacity(cmp::min(data.len() * 8 / 5, max_len));
+    let mut decoder = HuffmanDecoder::new();
+    track!(decoder.decode_with_limit(data, &mut buf, max_len))?;
+    track!(decoder.finish())?;
+    Ok(buf)
+}
+
+/// Streaming huffman decoder.
+///
+/// This accepts the encoded octets divided into arbitrary chunks.
+#[derive(Debug, Clone)]
+pub struct HuffmanDecoder {
+    state: u8,
+    flags: u8,
+}
+impl HuffmanDecoder {
+    /// Makes a new `HuffmanDecoder` instance.
+    pub fn new() -> Self {
+        HuffmanDecoder {
+            state: 0,
+            flags: FSM_ACCEPTED,
         }
+    }
 
-        self.offset = new_offset;
-        Ok(value)
+    /// Decodes a chunk of the encoded octets and appends the decoded octets to `buf`.
+    ///
+    /// # Errors
+    ///
+    /// If `data` contains the EOS symbol, an error will be returned.
+    pub fn decode(&mut self, data: &[u8], buf: &mut Vec<u8>) -> Result<()> {
+        track!(self.decode_with_limit(data, buf, usize::MAX))
     }
-}
 
-pub fn decode(data: &[u8]) -> Result<Vec<u8>> {
-    let mut buf = Vec::new();
-    let mut value = 0;
-    let mut next_bits = 5;
-    let mut acc_bits = 0;
-    let mut reader = BitReader::new(data);
-    while reader.remaining_bits() > 0 {
-        next_bits = cmp::min(next_bits, reader.remaining_bits());
-        value = (value << next_bits) + track!(reader.read_bits(next_bits))?;
-        acc_bits += next_bits;
-        if let Ok(i) = DECODING_TABLE.binary_search_by_key(&(value, acc_bits), |e| (e.0, e.1)) {
-            track_assert_ne!(i, 256, Failed);
-            buf.push(DECODING_TABLE[i].2 as u8);
-            next_bits = 5;
-            acc_bits = 0;
-            value = 0;
-        } else {
-            next_bits = 1;
+    /// Finishes the decoding.
+    ///
+    /// # Errors
+    ///
+    /// If the padding of the decoded octets is invalid, an error will be returned.
+    ///
+    /// See: [5.2.  String Literal Representation](https://tools.ietf.org/html/rfc7541#section-5.2)
+    pub fn finish(self) -> Result<()> {
+        track_assert_eq!(self.flags & FSM_LONG_PADDING, 0, ErrorKind::PaddingTooLong);
+        track_assert_ne!(self.flags & FSM_ACCEPTED, 0, ErrorKind::InvalidPadding);
+        Ok(())
+    }
+
+    fn decode_with_limit(&mut self, data: &[u8], buf: &mut Vec<u8>, max_len: usize) -> Result<()> {
+        let table = decoding_fsm();
+        for &b in data {
+            for &nibble in &[b >> 4, b & 0x0f] {
+                let t = table[self.state as usize][nibble as usize];
+                track_assert_eq!(t.flags & FSM_FAIL, 0, ErrorKind::InvalidHuffmanCode);
+                if t.flags & FSM_EMIT != 0 {
+                    track_assert!(
+                        buf.len() < max_len,
+                        ErrorKind::LimitExceeded,
+                        "Too long decoded string: max={}",
+                        max_len
+                    );
+                    buf.push(t.symbol);
+                }
+                self.state = t.state;
+                self.flags = t.flags;
+            }
         }
+        Ok(())
+    }
+}
+impl Default for HuffmanDecoder {
+    fn default() -> Self {
+        Self::new()
     }
-    track_assert_eq!(value, (1 << acc_bits) - 1, Failed);
-    Ok(buf)
 }
 
-#[derive(Debug)]
-struct BitWriter {
-    buf: Vec<u8>,
-    offset: usize,
+const FSM_EMIT: u8 = 0b001;
+const FSM_FAIL: u8 = 0b010;
+const FSM_ACCEPTED: u8 = 0b100;
+const FSM_LONG_PADDING: u8 = 0b1000;
+
+/// A transition of the decoding state machine.
+///
+/// The states of the machine are the internal nodes of the huffman tree,
+/// and each transition consumes 4 bits (i.e., at most one symbol is emitted per transition
+/// because the shortest code is 5 bits).
+#[derive(Debug, Clone, Copy, Default)]
+struct FsmTransition {
+    state: u8,
+    symbol: u8,
+    flags: u8,
 }
-impl BitWriter {
-    pub fn new() -> Self {
-        BitWriter {
-            buf: Vec::new(),
-            offset: 0,
-        }
+
+type DecodingFsm = [[FsmTransition; 16]; 256];
+
+fn decoding_fsm() -> &'static DecodingFsm {
+    static FSM: OnceLock<Box<DecodingFsm>> = OnceLock::new();
+    FSM.get_or_init(build_decoding_fsm)
+}
+
+fn build_decoding_fsm() -> Box<DecodingFsm> {
+    #[derive(Clone, Copy)]
+    enum Node {
+        Internal(usize),
+        Leaf(u16),
     }
-    pub fn write_bit(&mut self, bit: u8) {
-        if self.offset == 0 {
-            self.buf.push(0);
-            self.offset = 8;
+
+    // Builds the huffman tree from the canonical codes.
+    // `tree[i]` is the children of the i-th internal node (the 0-th one is the root),
+    // and `all_ones[i]` is the depth of the node if the path to it consists of only 1 bits
+    // (i.e., the node is reached by a prefix of EOS).
+    let mut tree: Vec<[Option<Node>; 2]> = vec![[None, None]];
+    let mut all_ones = vec![Some(0)];
+    for &(code, bits, symbol) in DECODING_TABLE.iter() {
+        let mut node = 0;
+        for i in (0..bits).rev() {
+            let bit = ((code >> i) & 1) as usize;
+            if i == 0 {
+                tree[node][bit] = Some(Node::Leaf(symbol));
+            } else if let Some(Node::Internal(child)) = tree[node][bit] {
+                node = child;
+            } else {
+                tree.push([None, None]);
+                all_ones.push(all_ones[node].filter(|_| bit == 1).map(|depth| depth + 1));
+                let child = tree.len() - 1;
+                tree[node][bit] = Some(Node::Internal(child));
+                node = child;
+            }
         }
-        self.offset -= 1;
-        let b = self.buf.last_mut().expect("Never fails");
-        *b |= bit << self.offset;
     }
-    pub fn finish(mut self) -> Vec<u8> {
-        for i in 0..self.offset {
-            let b = self.buf.last_mut().expect("Never fails");
-            *b |= 1 << i;
+    debug_assert_eq!(tree.len(), 256);
+
+    let mut fsm = Box::new([[FsmTransition::default(); 16]; 256]);
+    for (state, transitions) in fsm.iter_mut().enumerate() {
+        for (nibble, t) in transitions.iter_mut().enumerate() {
+            let mut node = state;
+            for i in (0..4).rev() {
+                match tree[node][(nibble >> i) & 1].expect("Never fails") {
+                    Node::Internal(child) => node = child,
+                    Node::Leaf(256) => t.flags |= FSM_FAIL,
+                    Node::Leaf(symbol) => {
+                        t.flags |= FSM_EMIT;
+                        t.symbol = symbol as u8;
+                        node = 0;
+                    }
+                }
+                if t.flags & FSM_FAIL != 0 {
+                    break;
+                }
+            }
+            t.state = node as u8;
+            match all_ones[node] {
+                Some(depth) if depth < 8 => t.flags |= FSM_ACCEPTED,
+                Some(_) => t.flags |= FSM_LONG_PADDING,
+                None => {}
+            }
         }
-        self.buf
     }
+    fsm
+}
+
+/// Returns the length of the huffman encoded octets of `data`.
+pub fn encoded_len(data: &[u8]) -> usize {
+    let bits = data
+        .iter()
+        .map(|b| ENCODING_TABLE[*b as usize].1 as usize)
+        .sum::<usize>();
+    bits.div_ceil(8)
 }
 
+/// Encodes `data` by huffman coding.
 pub fn encode(data: &[u8]) -> Vec<u8> {
-    // FIXME: optimize
-    let mut writer = BitWriter::new();
-    for b in data.iter() {
-        let (code, bitwidth) = ENCODING_TABLE[*b as usize];
-        for i in 0..bitwidth {
-            let bit = (code >> (bitwidth - i - 1)) & 0b1;
-            writer.write_bit(bit as u8);
+    let mut buf = Vec::with_capacity(encoded_len(data));
+    encode_into(data, &mut buf).expect("Never fails");
+    buf
+}
+
+/// Encodes `data` by huffman coding and writes the encoded octets to `writer`.
+///
+/// # Errors
+///
+/// If it fails to write to `writer`, an error will be returned.
+pub fn encode_into<W: Write>(data: &[u8], mut writer: W) -> Result<()> {
+    // The codes are packed into `acc` and flushed by 32 bits.
+    // Because the longest code is 30 bits, `acc` never holds more than 61 bits.
+    let mut acc: u64 = 0;
+    let mut acc_bits = 0;
+    let mut buf = [0; 256];
+    let mut buf_len = 0;
+    for &b in data {
+        let (code, bitwidth) = ENCODING_TABLE[b as usize];
+        acc = (acc << bitwidth) | u64::from(code);
+        acc_bits += u32::from(bitwidth);
+        if acc_bits >= 32 {
+            acc_bits -= 32;
+            let word = (acc >> acc_bits) as u32;
+            buf[buf_len..buf_len + 4].copy_from_slice(&word.to_be_bytes());
+            buf_len += 4;
+            if buf_len == buf.len() {
+                track_io!(writer.write_all(&buf))?;
+                buf_len = 0;
+            }
         }
     }
-    writer.finish()
+
+    // Pads the last octet with the most significant bits of EOS (i.e., all ones).
+    let padding = (8 - acc_bits % 8) % 8;
+    acc = (acc << padding) | ((1 << padding) - 1);
+    acc_bits += padding;
+    while acc_bits > 0 {
+        acc_bits -= 8;
+        buf[buf_len] = (acc >> acc_bits) as u8;
+        buf_len += 1;
+    }
+    track_io!(writer.write_all(&buf[..buf_len]))
 }
 
 const DECODING_TABLE: [(u32, usize, u16); 257] = [
@@ -616,3 +770,226 @@ const ENCODING_TABLE: [(u32, u8); 257] = [
     (0b1111_1111_1111_1111_1111_1011_10, 26),
     (0b1111_1111_1111_1111_1111_1111_1111_11, 30),
 ];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The previous (bit-by-bit) implementation of `decode` function,
+    // that is followed by the strict validation of the padding.
+    #[derive(Debug)]
+    struct BitReader<'a> {
+        data: &'a [u8],
+        offset: usize,
+    }
+    impl<'a> BitReader<'a> {
+        fn new(data: &'a [u8]) -> Self {
+            BitReader { data, offset: 0 }
+        }
+        fn remaining_bits(&self) -> usize {
+            self.data.len() * 8 - self.offset
+        }
+        fn read_bits(&mut self, bits: usize) -> Result<u32> {
+            debug_assert!((1..=5).contains(&bits));
+            debug_assert!(self.offset + bits <= self.data.len() * 8);
+            let new_offset = self.offset + bits;
+
+            let mut value = 0;
+            for i in self.offset..new_offset {
+                let bit = (self.data[i / 8] >> (7 - (i % 8))) & 0b1;
+                value = (value << 1) | bit as u32;
+            }
+
+            self.offset = new_offset;
+            Ok(value)
+        }
+    }
+
+    fn bitwise_decode(data: &[u8], max_len: usize) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        let mut value = 0;
+        let mut next_bits = 5;
+        let mut acc_bits = 0;
+        let mut reader = BitReader::new(data);
+        while reader.remaining_bits() > 0 {
+            next_bits = cmp::min(next_bits, reader.remaining_bits());
+            value = (value << next_bits) + track!(reader.read_bits(next_bits))?;
+            acc_bits += next_bits;
+            if let Ok(i) = DECODING_TABLE.binary_search_by_key(&(value, acc_bits), |e| (e.0, e.1)) {
+                track_assert_ne!(i, 256, ErrorKind::InvalidHuffmanCode);
+                track_assert!(
+                    buf.len() < max_len,
+                    ErrorKind::LimitExceeded,
+                    "Too long decoded string: max={}",
+                    max_len
+                );
+                buf.push(DECODING_TABLE[i].2 as u8);
+                next_bits = 5;
+                acc_bits = 0;
+                value = 0;
+            } else {
+                next_bits = 1;
+            }
+        }
+        if value == (1 << acc_bits) - 1 {
+            track_assert!(acc_bits < 8, ErrorKind::PaddingTooLong);
+        }
+        track_assert_eq!(value, (1 << acc_bits) - 1, ErrorKind::InvalidPadding);
+        Ok(buf)
+    }
+
+    #[derive(Debug)]
+    struct BitWriter {
+        buf: Vec<u8>,
+        offset: usize,
+    }
+    impl BitWriter {
+        fn new() -> Self {
+            BitWriter {
+                buf: Vec::new(),
+                offset: 0,
+            }
+        }
+        fn write_bit(&mut self, bit: u8) {
+            if self.offset == 0 {
+                self.buf.push(0);
+                self.offset = 8;
+            }
+            self.offset -= 1;
+            let b = self.buf.last_mut().expect("Never fails");
+            *b |= bit << self.offset;
+        }
+        fn finish(mut self) -> Vec<u8> {
+            for i in 0..self.offset {
+                let b = self.buf.last_mut().expect("Never fails");
+                *b |= 1 << i;
+            }
+            self.buf
+        }
+    }
+
+    // The previous (bit-by-bit) implementation of `encode` function.
+    fn bitwise_encode(data: &[u8]) -> Vec<u8> {
+        let mut writer = BitWriter::new();
+        for b in data.iter() {
+            let (code, bitwidth) = ENCODING_TABLE[*b as usize];
+            for i in 0..bitwidth {
+                let bit = (code >> (bitwidth - i - 1)) & 0b1;
+                writer.write_bit(bit as u8);
+            }
+        }
+        writer.finish()
+    }
+
+    fn assert_same_result(data: &[u8], max_len: usize) {
+        let expected = bitwise_decode(data, max_len).map_err(|e| *e.kind());
+        let actual = decode_with_limit(data, max_len).map_err(|e| *e.kind());
+        assert_eq!(actual, expected, "data={:?}, max_len={}", data, max_len);
+    }
+
+    #[test]
+    fn malformed_strings() {
+        // "a" (0b00011) with 3 bits padding
+        assert_eq!(track_try_unwrap!(decode(&[0b0001_1111])), b"a");
+
+        // Padding that is not a prefix of EOS
+        assert_eq!(
+            *decode(&[0b0001_1000]).err().unwrap().kind(),
+            ErrorKind::InvalidPadding
+        );
+        assert_eq!(
+            *decode(&[0b0001_1110]).err().unwrap().kind(),
+            ErrorKind::InvalidPadding
+        );
+
+        // Padding longer than 7 bits
+        assert_eq!(
+            *decode(&[0xff]).err().unwrap().kind(),
+            ErrorKind::PaddingTooLong
+        );
+        assert_eq!(
+            *decode(&[0b0001_1111, 0xff]).err().unwrap().kind(),
+            ErrorKind::PaddingTooLong
+        );
+        assert_eq!(
+            *decode(&[0xff, 0xff, 0xff]).err().unwrap().kind(),
+            ErrorKind::PaddingTooLong
+        );
+
+        // EOS symbol
+        assert_eq!(
+            *decode(&[0xff, 0xff, 0xff, 0xfc]).err().unwrap().kind(),
+            ErrorKind::InvalidHuffmanCode
+        );
+        assert_eq!(
+            *decode(&[0b0001_1111, 0xff, 0xff, 0xff, 0xe0])
+                .err()
+                .unwrap()
+                .kind(),
+            ErrorKind::InvalidHuffmanCode
+        );
+    }
+
+    #[test]
+    fn streaming_decoding() {
+        let encoded = encode(b"custom-value");
+        for chunk_size in 1..encoded.len() + 1 {
+            let mut decoder = HuffmanDecoder::new();
+            let mut decoded = Vec::new();
+            for chunk in encoded.chunks(chunk_size) {
+                track_try_unwrap!(decoder.decode(chunk, &mut decoded));
+            }
+            track_try_unwrap!(decoder.finish());
+            assert_eq!(decoded, b"custom-value");
+        }
+
+        let mut decoder = HuffmanDecoder::new();
+        let mut decoded = Vec::new();
+        track_try_unwrap!(decoder.decode(&encoded, &mut decoded));
+        track_try_unwrap!(decoder.decode(&[0xff], &mut decoded));
+        assert_eq!(
+            *decoder.finish().err().unwrap().kind(),
+            ErrorKind::PaddingTooLong
+        );
+    }
+
+    #[test]
+    fn decoding_fsm_is_equivalent_to_bitwise_decoding() {
+        // xorshift64
+        let mut seed = 0x2545_f491_4f6c_dd1d_u64;
+        let mut rand = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+
+        for _ in 0..10_000 {
+            let len = (rand() % 32) as usize;
+            let plain = (0..len).map(|_| rand() as u8).collect::<Vec<_>>();
+            let encoded = encode(&plain);
+            assert_eq!(encoded, bitwise_encode(&plain));
+            assert_eq!(encoded.len(), encoded_len(&plain));
+            assert_eq!(decode(&encoded).ok(), Some(plain.clone()));
+            assert_same_result(&encoded, usize::MAX);
+            assert_same_result(&encoded, len / 2);
+
+            // Corrupted data
+            let mut corrupted = encoded.clone();
+            if !corrupted.is_empty() {
+                let i = (rand() as usize) % corrupted.len();
+                corrupted[i] ^= rand() as u8;
+                assert_same_result(&corrupted, usize::MAX);
+            }
+            corrupted.push(rand() as u8);
+            assert_same_result(&corrupted, usize::MAX);
+
+            // Random data
+            let len = (rand() % 16) as usize;
+            let data = (0..len).map(|_| rand() as u8 | 0xe0).collect::<Vec<_>>();
+            assert_same_result(&data, usize::MAX);
+            let data = (0..len).map(|_| rand() as u8).collect::<Vec<_>>();
+            assert_same_result(&data, usize::MAX);
+        }
+    }
+}
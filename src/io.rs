@@ -1,17 +1,42 @@
-use crate::Result;
+use crate::literal;
+use crate::{DecoderConfig, ErrorKind, Result};
 use byteorder::ReadBytesExt;
 use std::cmp;
 use std::io::{Read, Result as IoResult};
-use trackable::error::Failed;
 
 #[derive(Debug)]
 pub struct SliceReader<'a> {
     slice: &'a [u8],
     offset: usize,
+    max_integer_value: u32,
+    max_string_length: usize,
+    strict_integer_encoding: bool,
 }
 impl<'a> SliceReader<'a> {
     pub fn new(slice: &'a [u8]) -> Self {
-        SliceReader { slice, offset: 0 }
+        SliceReader {
+            slice,
+            offset: 0,
+            max_integer_value: u32::MAX,
+            max_string_length: usize::MAX,
+            strict_integer_encoding: false,
+        }
+    }
+    pub fn set_limits(&mut self, config: &DecoderConfig) {
+        self.max_integer_value = config.max_integer_value;
+        self.max_string_length = config.max_string_length;
+        self.strict_integer_encoding = config.strict_integer_encoding;
+    }
+    pub fn max_string_length(&self) -> usize {
+        self.max_string_length
+    }
+    pub fn read_integer(&mut self, prefix_bits: u8) -> Result<(u8, u32)> {
+        let max = self.max_integer_value;
+        let strict = self.strict_integer_encoding;
+        track!(literal::decode_u32(self, prefix_bits, max, strict))
+    }
+    pub fn position(&self) -> usize {
+        self.offset
     }
     pub fn eos(&self) -> bool {
         debug_assert!(self.offset <= self.slice.len());
@@ -28,7 +53,7 @@ impl<'a> SliceReader<'a> {
     pub fn read_slice(&mut self, size: usize) -> Result<&'a [u8]> {
         track_assert!(
             self.offset + size <= self.slice.len(),
-            Failed,
+            ErrorKind::TruncatedInput,
             "offset={}, size={}, slice.len={}",
             self.offset,
             size,
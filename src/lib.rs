@@ -32,28 +32,27 @@ extern crate trackable;
 
 macro_rules! track_io {
     ($e:expr) => {
-        $e.map_err(|e| {
-            use trackable::error::{ErrorKindExt, Failed};
-            Failed.cause(e)
-        })
+        track!($e.map_err(crate::Error::from))
     };
 }
 
-pub use decoder::{Decoder, HeaderBlockDecoder};
-pub use encoder::{Encoder, HeaderBlockEncoder};
+pub use decoder::{Decoder, DecoderConfig, FragmentedHeaderBlockDecoder, HeaderBlockDecoder};
+pub use encoder::{Encoder, FragmentWriter, HeaderBlockEncoder};
+pub use error::{Error, ErrorKind};
+pub use transcoder::Transcoder;
 
 pub mod field;
+pub mod huffman;
 pub mod literal;
+pub mod policy;
 pub mod table;
 
 mod decoder;
 mod encoder;
-mod huffman;
+mod error;
 mod io;
 mod signal;
-
-/// This crate specific `Error` type.
-pub type Error = trackable::error::TrackableError<trackable::error::Failed>;
+mod transcoder;
 
 /// This crate specific `Result` type.
 pub type Result<T, E = Error> = std::result::Result<T, E>;
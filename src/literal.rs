@@ -1,23 +1,22 @@
 //! Literal types.
 use crate::huffman;
 use crate::io::SliceReader;
-use crate::Result;
+use crate::{ErrorKind, Result};
 use byteorder::{ReadBytesExt, WriteBytesExt};
 use std::borrow::Cow;
+use std::cmp;
 use std::io::{Read, Write};
-use std::u16;
-use trackable::error::Failed;
 
-pub(crate) fn encode_u16<W: Write>(
+pub(crate) fn encode_u32<W: Write>(
     mut writer: W,
     prepended_value: u8,
     prefix_bits: u8,
-    value: u16,
+    value: u32,
 ) -> Result<()> {
-    debug_assert!(1 <= prefix_bits && prefix_bits <= 8);
-    let max_prefix_value: u16 = (1 << prefix_bits) - 1;
+    debug_assert!((1..=8).contains(&prefix_bits));
+    let max_prefix_value: u32 = (1 << prefix_bits) - 1;
     if value < max_prefix_value {
-        let first_octet = (((prepended_value as u16) << prefix_bits) | value) as u8;
+        let first_octet = (((prepended_value as u32) << prefix_bits) | value) as u8;
         track_io!(writer.write_u8(first_octet))?;
     } else {
         let first_octet = (prepended_value << prefix_bits) | (max_prefix_value as u8);
@@ -32,29 +31,90 @@ pub(crate) fn encode_u16<W: Write>(
     Ok(())
 }
 
-pub(crate) fn decode_u16<R: Read>(mut reader: R, prefix_bits: u8) -> Result<(u8, u16)> {
-    debug_assert!(1 <= prefix_bits && prefix_bits <= 8);
-    let max_prefix_value: u16 = (1 << prefix_bits) - 1;
+/// Decodes an integer.
+///
+/// The number of continuation octets is limited to the minimum number needed to
+/// represent `max_value`. If `strict` is `true`, non-minimal encodings
+/// (i.e., the ones terminated by a redundant zero octet) are also rejected.
+pub(crate) fn decode_u32<R: Read>(
+    mut reader: R,
+    prefix_bits: u8,
+    max_value: u32,
+    strict: bool,
+) -> Result<(u8, u32)> {
+    debug_assert!((1..=8).contains(&prefix_bits));
+    let max_prefix_value: u32 = (1 << prefix_bits) - 1;
     let first_octet = track_io!(reader.read_u8())?;
-    let prepended_value = ((first_octet as u16) >> prefix_bits) as u8;
-    let mut value = first_octet as u16 & max_prefix_value;
-    if value == max_prefix_value {
-        let mut offset = 0;
-        let mut octet = 128;
-        while octet & 128 == 128 {
-            octet = track_io!(reader.read_u8())?;
-
-            let addition = (octet as u16 & 127) << offset;
-            value = track_assert_some!(
-                value.checked_add(addition),
-                Failed,
-                "Too large integer: {}",
-                value as u32 + addition as u32
+    let prepended_value = ((first_octet as u32) >> prefix_bits) as u8;
+    let mut value = u64::from(first_octet as u32 & max_prefix_value);
+    if value == u64::from(max_prefix_value) {
+        let max_bits = 32 - max_value.saturating_sub(max_prefix_value).leading_zeros();
+        let max_octets = cmp::max(1, max_bits.div_ceil(7));
+        let mut octets = 0;
+        loop {
+            let octet = track_io!(reader.read_u8())?;
+            track_assert!(
+                octets < max_octets,
+                ErrorKind::OverlongInteger,
+                "Too many continuation octets: max={}",
+                max_octets
+            );
+            value += u64::from(octet & 127) << (octets * 7);
+            octets += 1;
+            track_assert!(
+                value <= u64::from(max_value),
+                ErrorKind::IntegerOverflow,
+                "Too large integer: {} (max={})",
+                value,
+                max_value
             );
-            offset += 7;
+            if octet & 128 == 0 {
+                track_assert!(
+                    !strict || octets == 1 || octet != 0,
+                    ErrorKind::NonMinimalInteger,
+                    "Redundant continuation octet: value={}",
+                    value
+                );
+                break;
+            }
+        }
+    }
+    track_assert!(
+        value <= u64::from(max_value),
+        ErrorKind::IntegerOverflow,
+        "Too large integer: {} (max={})",
+        value,
+        max_value
+    );
+    Ok((prepended_value, value as u32))
+}
+
+/// Strategy for applying huffman coding to string literals.
+///
+/// See: [5.2.  String Literal Representation](https://tools.ietf.org/html/rfc7541#section-5.2)
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum HuffmanCoding {
+    /// String literals are encoded as plain octets.
+    #[default]
+    Never,
+
+    /// String literals are always encoded by huffman coding.
+    Always,
+
+    /// Selects the shorter representation for each string literal.
+    ///
+    /// If the huffman encoded octets and the plain octets have the same length,
+    /// the plain octets are selected.
+    Auto,
+}
+impl HuffmanCoding {
+    fn is_applied_to(self, octets: &[u8]) -> bool {
+        match self {
+            HuffmanCoding::Never => false,
+            HuffmanCoding::Always => true,
+            HuffmanCoding::Auto => huffman::encoded_len(octets) < octets.len(),
         }
     }
-    Ok((prepended_value, value))
 }
 
 /// HPACK String type.
@@ -71,16 +131,62 @@ pub enum HpackString<'a> {
     Huffman(Cow<'a, [u8]>),
 }
 impl<'a> HpackString<'a> {
-    pub(crate) fn to_plain_bytes(&self) -> Result<Cow<[u8]>> {
+    /// Makes a new `HpackString` instance from the plain octets.
+    ///
+    /// The representation of the resulting string is decided by `coding`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hpack_codec::literal::{HpackString, HuffmanCoding};
+    ///
+    /// let s = HpackString::new(b"www.example.com", HuffmanCoding::Auto);
+    /// assert!(s.is_huffman_encoded());
+    ///
+    /// let s = HpackString::new(b"\x00\x01\x02", HuffmanCoding::Auto);
+    /// assert!(!s.is_huffman_encoded());
+    /// ```
+    pub fn new(octets: &'a [u8], coding: HuffmanCoding) -> Self {
+        HpackString::Plain(Cow::Borrowed(octets)).with_huffman_coding(coding)
+    }
+
+    /// Returns `true` if this string is encoded by huffman coding, otherwise `false`.
+    pub fn is_huffman_encoded(&self) -> bool {
+        matches!(*self, HpackString::Huffman(_))
+    }
+
+    /// Returns the length of the encoded octets of this string.
+    pub fn len(&self) -> usize {
+        match *self {
+            HpackString::Plain(ref x) | HpackString::Huffman(ref x) => x.len(),
+        }
+    }
+
+    /// Returns `true` if this string has no octets, otherwise `false`.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub(crate) fn with_huffman_coding(self, coding: HuffmanCoding) -> Self {
+        match self {
+            HpackString::Plain(ref x) if coding.is_applied_to(x) => {
+                HpackString::Huffman(Cow::Owned(huffman::encode(x)))
+            }
+            s => s,
+        }
+    }
+    pub(crate) fn to_plain_bytes(&self) -> Result<Cow<'_, [u8]>> {
         match *self {
             HpackString::Plain(ref x) => Ok(Cow::Borrowed(x.as_ref())),
             HpackString::Huffman(ref x) => Ok(Cow::Owned(track!(huffman::decode(x))?)),
         }
     }
-    pub(crate) fn into_plain_bytes(self) -> Result<Cow<'a, [u8]>> {
+    pub(crate) fn into_plain_bytes(self, max_len: usize) -> Result<Cow<'a, [u8]>> {
         match self {
             HpackString::Plain(x) => Ok(x),
-            HpackString::Huffman(x) => Ok(Cow::Owned(track!(huffman::decode(x.as_ref()))?)),
+            HpackString::Huffman(x) => {
+                Ok(Cow::Owned(track!(huffman::decode_with_limit(&x, max_len))?))
+            }
         }
     }
     pub(crate) fn encode<W: Write>(&self, mut writer: W) -> Result<()> {
@@ -88,13 +194,49 @@ impl<'a> HpackString<'a> {
             HpackString::Plain(ref x) => (0, x.as_ref()),
             HpackString::Huffman(ref x) => (1, x.as_ref()),
         };
-        debug_assert!(octets.len() <= u16::MAX as usize);
-        track!(encode_u16(&mut writer, encoding, 7, octets.len() as u16))?;
+        track_assert!(
+            octets.len() <= u32::MAX as usize,
+            ErrorKind::InvalidInput,
+            "Too large string: {}",
+            octets.len()
+        );
+        track!(encode_u32(&mut writer, encoding, 7, octets.len() as u32))?;
         track_io!(writer.write_all(octets))?;
         Ok(())
     }
-    pub(crate) fn decode(mut reader: &mut SliceReader<'a>) -> Result<Self> {
-        let (encoding, octets_len) = track!(decode_u16(&mut reader, 7))?;
+    /// Encodes this string, applying huffman coding to the plain octets in accordance with `coding`.
+    ///
+    /// Unlike `with_huffman_coding`, no intermediate buffers are allocated.
+    pub(crate) fn encode_with_huffman_coding<W: Write>(
+        &self,
+        mut writer: W,
+        coding: HuffmanCoding,
+    ) -> Result<()> {
+        match *self {
+            HpackString::Plain(ref x) if coding.is_applied_to(x) => {
+                let encoded_len = huffman::encoded_len(x);
+                track_assert!(
+                    encoded_len <= u32::MAX as usize,
+                    ErrorKind::InvalidInput,
+                    "Too large string: {}",
+                    encoded_len
+                );
+                track!(encode_u32(&mut writer, 1, 7, encoded_len as u32))?;
+                track!(huffman::encode_into(x, writer))?;
+                Ok(())
+            }
+            _ => track!(self.encode(writer)),
+        }
+    }
+    pub(crate) fn decode(reader: &mut SliceReader<'a>) -> Result<Self> {
+        let (encoding, octets_len) = track!(reader.read_integer(7))?;
+        track_assert!(
+            octets_len as usize <= reader.max_string_length(),
+            ErrorKind::LimitExceeded,
+            "Too long string: length={}, max={}",
+            octets_len,
+            reader.max_string_length()
+        );
         let octets = Cow::Borrowed(track!(reader.read_slice(octets_len as usize))?);
         if encoding == 0 {
             Ok(HpackString::Plain(octets))
@@ -108,14 +250,86 @@ impl<'a> HpackString<'a> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn encoding_large_integers() {
+        let mut buf = Vec::new();
+        track_try_unwrap!(encode_u32(&mut buf, 0, 5, u32::MAX));
+        assert_eq!(buf, [0b000_11111, 0xe0, 0xff, 0xff, 0xff, 0x0f]);
+
+        let (_, value) = track_try_unwrap!(decode_u32(&buf[..], 5, u32::MAX, false));
+        assert_eq!(value, u32::MAX);
+
+        // Exceeds the maximum value
+        assert!(decode_u32(&buf[..], 5, u32::MAX - 1, false).is_err());
+
+        // Overflow
+        assert!(decode_u32(
+            &[0b000_11111, 0xe1, 0xff, 0xff, 0xff, 0x0f][..],
+            5,
+            u32::MAX,
+            false
+        )
+        .is_err());
+        assert!(decode_u32(
+            &[0b000_11111, 0x80, 0x80, 0x80, 0x80, 0x10][..],
+            5,
+            u32::MAX,
+            false
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn overlong_integers() {
+        // 41 (= 31 + 10) with redundant continuation octets
+        let (_, value) = track_try_unwrap!(decode_u32(&[0x1f, 0x8a, 0x00][..], 5, u32::MAX, false));
+        assert_eq!(value, 41);
+        let e = decode_u32(&[0x1f, 0x8a, 0x00][..], 5, u32::MAX, true).err();
+        assert_eq!(*e.unwrap().kind(), ErrorKind::NonMinimalInteger);
+
+        // Zero continuation octet is minimal
+        let (_, value) = track_try_unwrap!(decode_u32(&[0x1f, 0x00][..], 5, u32::MAX, true));
+        assert_eq!(value, 31);
+
+        // Up to five continuation octets are acceptable for 32-bit integers
+        let data = [0x1f, 0x80, 0x80, 0x80, 0x80, 0x00];
+        assert!(decode_u32(&data[..], 5, u32::MAX, false).is_ok());
+        let data = [0x1f, 0x80, 0x80, 0x80, 0x80, 0x80, 0x00];
+        let e = decode_u32(&data[..], 5, u32::MAX, false).err();
+        assert_eq!(*e.unwrap().kind(), ErrorKind::OverlongInteger);
+
+        // The maximum number of continuation octets depends on the maximum value
+        let data = [0x1f, 0x80, 0x00];
+        assert!(decode_u32(&data[..], 5, 1000, false).is_ok());
+        let data = [0x1f, 0x80, 0x80, 0x00];
+        let e = decode_u32(&data[..], 5, 1000, false).err();
+        assert_eq!(*e.unwrap().kind(), ErrorKind::OverlongInteger);
+    }
+
+    #[test]
+    fn auto_huffman_coding_selects_shorter_representation() {
+        let s = HpackString::new(b"custom-key", HuffmanCoding::Auto);
+        assert!(s.is_huffman_encoded());
+        assert_eq!(s.len(), 8);
+
+        let s = HpackString::new(b"{~^}|\\<>", HuffmanCoding::Auto);
+        assert!(!s.is_huffman_encoded());
+        assert_eq!(s.len(), 8);
+
+        let s = HpackString::new(b"{~^}|\\<>", HuffmanCoding::Always);
+        assert!(s.is_huffman_encoded());
+        assert_eq!(s.len(), huffman::encoded_len(b"{~^}|\\<>"));
+        assert!(s.len() > 8);
+    }
+
     #[test]
     /// https://tools.ietf.org/html/rfc7541#appendix-C.1.1
     fn encoding_10_using_a_5bit_prefix() {
         let mut buf = [0; 1];
-        track_try_unwrap!(encode_u16(&mut buf[..], 0b110, 5, 10));
+        track_try_unwrap!(encode_u32(&mut buf[..], 0b110, 5, 10));
         assert_eq!(buf, [0b110_01010]);
 
-        let (prepended, value) = track_try_unwrap!(decode_u16(&buf[..], 5));
+        let (prepended, value) = track_try_unwrap!(decode_u32(&buf[..], 5, u32::MAX, false));
         assert_eq!(prepended, 0b110);
         assert_eq!(value, 10);
     }
@@ -124,10 +338,10 @@ mod tests {
     /// https://tools.ietf.org/html/rfc7541#appendix-C.1.2
     fn encoding_1337_using_a_5bit_prefix() {
         let mut buf = [0; 3];
-        track_try_unwrap!(encode_u16(&mut buf[..], 0b110, 5, 1337));
+        track_try_unwrap!(encode_u32(&mut buf[..], 0b110, 5, 1337));
         assert_eq!(buf, [0b110_11111, 0b10011010, 0b00001010]);
 
-        let (prepended, value) = track_try_unwrap!(decode_u16(&buf[..], 5));
+        let (prepended, value) = track_try_unwrap!(decode_u32(&buf[..], 5, u32::MAX, false));
         assert_eq!(prepended, 0b110);
         assert_eq!(value, 1337);
     }
@@ -136,10 +350,10 @@ mod tests {
     /// https://tools.ietf.org/html/rfc7541#appendix-C.1.3
     fn encoding_42_starting_at_an_octet_boundary() {
         let mut buf = [0; 1];
-        track_try_unwrap!(encode_u16(&mut buf[..], 0, 8, 42));
+        track_try_unwrap!(encode_u32(&mut buf[..], 0, 8, 42));
         assert_eq!(buf, [0b00101010]);
 
-        let (prepended, value) = track_try_unwrap!(decode_u16(&buf[..], 8));
+        let (prepended, value) = track_try_unwrap!(decode_u32(&buf[..], 8, u32::MAX, false));
         assert_eq!(prepended, 0);
         assert_eq!(value, 42);
     }
@@ -0,0 +1,159 @@
+//! Indexing policies.
+//!
+//! An indexing policy decides which header fields will be inserted into the dynamic table
+//! when they are encoded by `HeaderBlockEncoder::encode_header` method.
+use crate::field::LiteralFieldForm;
+use std::fmt;
+
+/// This trait allows for deciding the representation of header fields to be encoded.
+pub trait IndexingPolicy: fmt::Debug {
+    /// Returns the form used for encoding the given header field as a literal.
+    ///
+    /// If `LiteralFieldForm::NeverIndexed` is returned,
+    /// the field will be encoded as a literal even if the table contains an entry exactly matching it.
+    fn literal_form(&self, name: &[u8], value: &[u8]) -> LiteralFieldForm;
+}
+impl<P: IndexingPolicy + ?Sized> IndexingPolicy for &P {
+    fn literal_form(&self, name: &[u8], value: &[u8]) -> LiteralFieldForm {
+        (**self).literal_form(name, value)
+    }
+}
+impl<P: IndexingPolicy + ?Sized> IndexingPolicy for Box<P> {
+    fn literal_form(&self, name: &[u8], value: &[u8]) -> LiteralFieldForm {
+        (**self).literal_form(name, value)
+    }
+}
+
+/// A policy that indexes every header field.
+///
+/// This is the default policy of `Encoder`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct AlwaysIndexing;
+impl IndexingPolicy for AlwaysIndexing {
+    fn literal_form(&self, _name: &[u8], _value: &[u8]) -> LiteralFieldForm {
+        LiteralFieldForm::WithIndexing
+    }
+}
+
+/// A policy that never indexes header fields.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NeverIndexing;
+impl IndexingPolicy for NeverIndexing {
+    fn literal_form(&self, _name: &[u8], _value: &[u8]) -> LiteralFieldForm {
+        LiteralFieldForm::WithoutIndexing
+    }
+}
+
+/// A policy that indexes only the header fields which have entry sizes
+/// less than or equal to the given limit.
+///
+/// See: [4.1.  Calculating Table Size](https://tools.ietf.org/html/rfc7541#section-4.1)
+#[derive(Debug, Clone, Copy)]
+pub struct SizeBoundedIndexing {
+    max_entry_size: usize,
+}
+impl SizeBoundedIndexing {
+    /// Makes a new `SizeBoundedIndexing` instance.
+    pub fn new(max_entry_size: usize) -> Self {
+        SizeBoundedIndexing { max_entry_size }
+    }
+
+    /// Returns the maximum entry size of indexable header fields.
+    pub fn max_entry_size(&self) -> usize {
+        self.max_entry_size
+    }
+}
+impl IndexingPolicy for SizeBoundedIndexing {
+    fn literal_form(&self, name: &[u8], value: &[u8]) -> LiteralFieldForm {
+        if name.len() + value.len() + 32 <= self.max_entry_size {
+            LiteralFieldForm::WithIndexing
+        } else {
+            LiteralFieldForm::WithoutIndexing
+        }
+    }
+}
+
+/// A policy that encodes sensitive header fields as never indexed literals.
+///
+/// The `authorization`, `cookie` and `set-cookie` headers are regarded as sensitive.
+/// The forms of the other header fields are decided by the inner policy.
+///
+/// See: [7.1.3.  Never-Indexed Literals](https://tools.ietf.org/html/rfc7541#section-7.1.3)
+#[derive(Debug, Default, Clone)]
+pub struct SensitiveHeaders<P = AlwaysIndexing> {
+    inner: P,
+}
+impl<P: IndexingPolicy> SensitiveHeaders<P> {
+    /// Makes a new `SensitiveHeaders` instance.
+    pub fn new(inner: P) -> Self {
+        SensitiveHeaders { inner }
+    }
+
+    /// Returns a reference to the inner policy.
+    pub fn inner_ref(&self) -> &P {
+        &self.inner
+    }
+
+    /// Takes ownership of the instance, and returns the inner policy.
+    pub fn into_inner(self) -> P {
+        self.inner
+    }
+}
+impl<P: IndexingPolicy> IndexingPolicy for SensitiveHeaders<P> {
+    fn literal_form(&self, name: &[u8], value: &[u8]) -> LiteralFieldForm {
+        if is_sensitive(name) {
+            LiteralFieldForm::NeverIndexed
+        } else {
+            self.inner.literal_form(name, value)
+        }
+    }
+}
+
+/// Returns `true` if the header named `name` is regarded as sensitive by `SensitiveHeaders`,
+/// otherwise `false`.
+pub fn is_sensitive(name: &[u8]) -> bool {
+    [&b"authorization"[..], b"cookie", b"set-cookie"]
+        .iter()
+        .any(|n| n.eq_ignore_ascii_case(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn size_bounded_indexing_works() {
+        let policy = SizeBoundedIndexing::new(40);
+        assert_eq!(
+            policy.literal_form(b"foo", b"bar"),
+            LiteralFieldForm::WithIndexing
+        );
+        assert_eq!(
+            policy.literal_form(b"foo", b"barbaz"),
+            LiteralFieldForm::WithoutIndexing
+        );
+    }
+
+    #[test]
+    fn sensitive_headers_works() {
+        let policy = SensitiveHeaders::new(NeverIndexing);
+        assert_eq!(
+            policy.literal_form(b"cookie", b"foo=bar"),
+            LiteralFieldForm::NeverIndexed
+        );
+        assert_eq!(
+            policy.literal_form(b"Authorization", b"secret"),
+            LiteralFieldForm::NeverIndexed
+        );
+        assert_eq!(
+            policy.literal_form(b"set-cookie", b"foo=bar"),
+            LiteralFieldForm::NeverIndexed
+        );
+        assert_eq!(
+            policy.literal_form(b"content-type", b"text/plain"),
+            LiteralFieldForm::WithoutIndexing
+        );
+        assert!(is_sensitive(b"Set-Cookie"));
+        assert!(!is_sensitive(b"content-type"));
+    }
+}
@@ -1,28 +1,41 @@
 //! Header Field Table.
 //!
 //! See: [2.3.  Indexing Tables](https://tools.ietf.org/html/rfc7541#section-2.3)
-use crate::field::HeaderField;
-use crate::Result;
-use std::borrow::Cow;
-use std::collections::VecDeque;
+use crate::field::{HeaderField, Octets};
+use crate::{ErrorKind, Result};
+use std::collections::hash_map::RandomState;
+use std::collections::{vec_deque, HashMap, VecDeque};
+use std::hash::{BuildHasher, Hasher};
 use std::ops::{Add, AddAssign};
-use trackable::error::Failed;
+use std::sync::Arc;
+use std::{cmp, fmt};
 
 /// Table for associating header fields to indexes.
 ///
 /// See: [2.3.  Indexing Tables](https://tools.ietf.org/html/rfc7541#section-2.3)
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Table {
     dynamic_table: DynamicTable,
 }
 impl Table {
     /// Makes a new `Table` instance.
-    pub fn new(max_dynamic_table_size: u16) -> Self {
+    pub fn new(max_dynamic_table_size: u32) -> Self {
         Table {
             dynamic_table: DynamicTable::new(max_dynamic_table_size),
         }
     }
 
+    /// Makes a new `Table` instance which maintains a hash index over the dynamic table.
+    ///
+    /// The index is updated when entries are inserted or evicted,
+    /// and makes `Table::find` run in constant time regardless of the number of the entries.
+    /// This is suitable for encoders.
+    pub fn with_field_index(max_dynamic_table_size: u32) -> Self {
+        let mut dynamic_table = DynamicTable::new(max_dynamic_table_size);
+        dynamic_table.field_index = Some(FieldIndex::default());
+        Table { dynamic_table }
+    }
+
     /// Returns the reference to `DynamicTable` instance.
     pub fn dynamic(&self) -> &DynamicTable {
         &self.dynamic_table
@@ -35,36 +48,103 @@ impl Table {
 
     /// Returns the entry associated with the specified index.
     ///
+    /// The octets of a dynamic table entry are copied out of the ring buffer of the table
+    /// into a buffer held only by the returned field (and its clones),
+    /// so the field can outlive the eviction of the entry.
+    ///
     /// # Errors
     ///
     /// If `index` value is too large, an error will be returned.
-    pub fn get(&self, index: Index) -> Result<HeaderField> {
+    pub fn get(&self, index: Index) -> Result<HeaderField<'_>> {
         if let Some(entry) = StaticEntry::from_index(index) {
             Ok(entry.into())
         } else {
             let dynamic_table_entry_index =
-                (index.as_u16() - Index::dynamic_table_offset().as_u16()) as usize;
+                (index.as_u32() - Index::dynamic_table_offset().as_u32()) as usize;
             let entry = track_assert_some!(
-                self.dynamic_table.entries().get(dynamic_table_entry_index),
-                Failed,
+                self.dynamic_table.entry(dynamic_table_entry_index),
+                ErrorKind::InvalidIndex,
                 "Too large index: {:?}",
                 index
             );
-            Ok(entry.as_borrowed())
+            Ok(entry)
         }
     }
 
     /// Returns the number of indexed entries.
-    pub fn len(&self) -> u16 {
-        (StaticEntry::entries_count() + self.dynamic_table.entries().len()) as u16
+    #[allow(clippy::len_without_is_empty)]
+    pub fn len(&self) -> u32 {
+        (StaticEntry::entries_count() + self.dynamic_table.len()) as u32
+    }
+
+    /// Searches the entry which matches the given field.
+    ///
+    /// The second element of the result indicates whether the value of the entry
+    /// is also matched (`true`) or only the name is matched (`false`).
+    /// If there are multiple candidates, the entry of the static table
+    /// or the newest entry of the dynamic table is preferred.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hpack_codec::Encoder;
+    /// use hpack_codec::table::{Index, StaticEntry};
+    ///
+    /// let mut encoder = Encoder::new(4096);
+    /// {
+    ///     let mut block = encoder.enter_header_block(Vec::new()).unwrap();
+    ///     block.encode_header(b"foo", b"bar").unwrap();
+    ///     block.encode_header(b"baz", b"qux").unwrap();
+    ///     block.finish();
+    /// }
+    ///
+    /// let table = encoder.table();
+    /// let offset = Index::dynamic_table_offset();
+    /// assert_eq!(table.find(b":method", b"GET"), Some((StaticEntry::MethodGet.into(), true)));
+    /// assert_eq!(table.find(b":method", b"PUT"), Some((StaticEntry::MethodGet.into(), false)));
+    /// assert_eq!(table.find(b"foo", b"bar"), Some((offset + 1, true)));
+    /// assert_eq!(table.find(b"baz", b"quux"), Some((offset, false)));
+    /// assert_eq!(table.find(b"quux", b""), None);
+    /// ```
+    pub fn find(&self, name: &[u8], value: &[u8]) -> Option<(Index, bool)> {
+        if let Some(entry) = StaticEntry::find(name, value) {
+            return Some((Index::from(entry), true));
+        }
+        let static_name_matched = StaticEntry::find_name(name).map(Index::from);
+
+        let (matched, name_matched) = self.dynamic_table.find(name, value);
+        if let Some(i) = matched {
+            return Some((Index::dynamic_table_offset() + i as u32, true));
+        }
+        static_name_matched
+            .or_else(|| name_matched.map(|i| Index::dynamic_table_offset() + i as u32))
+            .map(|index| (index, false))
+    }
+
+    /// Adds an entry whose name is the one of the entry associated with `index`.
+    ///
+    /// The referenced name is kept even if the entry is evicted by this insertion.
+    pub(crate) fn push_with_indexed_name(
+        &mut self,
+        index: Index,
+        value: &[u8],
+    ) -> Result<Option<HeaderField<'static>>> {
+        if let Some(entry) = StaticEntry::from_index(index) {
+            let entry = HeaderField::from(entry);
+            Ok(self.dynamic_table.push(entry.name(), value))
+        } else {
+            track!(self.validate_index(index))?;
+            let i = (index.as_u32() - Index::dynamic_table_offset().as_u32()) as usize;
+            Ok(self.dynamic_table.push_with_entry_name(i, value))
+        }
     }
 
     pub(crate) fn validate_index(&self, index: Index) -> Result<()> {
-        let index = index.as_u16();
+        let index = index.as_u32();
         let max_index = self.len();
         track_assert!(
             index <= max_index,
-            Failed,
+            ErrorKind::InvalidIndex,
             "Too large index: {} (max={})",
             index,
             max_index
@@ -75,54 +155,104 @@ impl Table {
 
 /// Dynamic Indexing Table.
 ///
+/// The octets of the entries are stored in a ring buffer which never exceeds
+/// the soft limit of the table size, so inserting and evicting entries
+/// do not allocate memory once the buffer has grown up to the limit.
+///
+/// The ring buffer is the only storage held by this table.
+/// The fields derived from this table (e.g., the ones returned by `Table::get`)
+/// hold their own copies of the octets, which are made at each retrieval
+/// and never kept by the table.
+///
 /// See: [2.3.2.  Dynamic Table](https://tools.ietf.org/html/rfc7541#section-2.3.2)
-#[derive(Debug)]
+#[derive(Clone)]
 pub struct DynamicTable {
-    entries: VecDeque<HeaderField<'static>>,
-    size: u16,
-    size_soft_limit: u16,
-    size_hard_limit: u16,
+    buffer: Box<[u8]>,
+    head: usize,
+    used: usize,
+    entries: VecDeque<EntryLocation>,
+    name_buffer: Vec<u8>,
+    inserted: u64,
+    field_index: Option<FieldIndex>,
+    journal: Option<Journal>,
+    size: u32,
+    size_soft_limit: u32,
+    size_hard_limit: u32,
 }
 impl DynamicTable {
-    pub(crate) fn new(max_size: u16) -> Self {
+    pub(crate) fn new(max_size: u32) -> Self {
         DynamicTable {
+            buffer: Box::default(),
+            head: 0,
+            used: 0,
             entries: VecDeque::new(),
+            name_buffer: Vec::new(),
+            inserted: 0,
+            field_index: None,
+            journal: None,
             size: 0,
             size_soft_limit: max_size,
             size_hard_limit: max_size,
         }
     }
 
-    /// Returns the reference to the dynamically indexed entries
-    pub fn entries(&self) -> &VecDeque<HeaderField<'static>> {
-        &self.entries
+    /// Returns an iterator over the dynamically indexed entries.
+    ///
+    /// The entries are ordered from the newest one to the oldest one.
+    pub fn entries(&self) -> Entries<'_> {
+        Entries {
+            table: self,
+            entries: self.entries.iter(),
+        }
+    }
+
+    /// Returns the `i`-th newest entry of this table.
+    pub fn entry(&self, i: usize) -> Option<HeaderField<'_>> {
+        self.entries.get(i).map(|e| self.field(e))
+    }
+
+    /// Returns the number of the entries of this table.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns `true` if this table has no entries, otherwise `false`.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
     }
 
     /// Returns the size of this table.
     ///
     /// See: [4.1.  Calculating Table Size](https://tools.ietf.org/html/rfc7541#section-4.1)
-    pub fn size(&self) -> u16 {
+    pub fn size(&self) -> u32 {
         self.size
     }
 
+    /// Returns the number of octets allocated for storing the entries of this table.
+    ///
+    /// This never exceeds the soft limit of the size of this table.
+    pub fn capacity(&self) -> usize {
+        self.buffer.len()
+    }
+
     /// Returns the hard limit of the size of this table.
     ///
     /// See: [4.2.  Maximum Table Size](https://tools.ietf.org/html/rfc7541#section-4.2)
-    pub fn size_hard_limit(&self) -> u16 {
+    pub fn size_hard_limit(&self) -> u32 {
         self.size_hard_limit
     }
 
     /// Returns the soft limit of the size of this table.
     ///
     /// See: [4.2.  Maximum Table Size](https://tools.ietf.org/html/rfc7541#section-4.2)
-    pub fn size_soft_limit(&self) -> u16 {
+    pub fn size_soft_limit(&self) -> u32 {
         self.size_soft_limit
     }
 
     /// Sets the hard limit of the size of this table.
     ///
     /// Note that the soft limit will be truncated to `max_size` if it is greater than `max_size`.
-    pub fn set_size_hard_limit(&mut self, max_size: u16) {
+    pub fn set_size_hard_limit(&mut self, max_size: u32) {
         self.size_hard_limit = max_size;
         if self.size_hard_limit < self.size_soft_limit {
             self.set_size_soft_limit(max_size).expect("Never fails");
@@ -134,44 +264,489 @@ impl DynamicTable {
     /// # Errors
     ///
     /// If `max_size` exceeds the hard limit of this table, an error will be returned.
-    pub fn set_size_soft_limit(&mut self, max_size: u16) -> Result<()> {
+    pub fn set_size_soft_limit(&mut self, max_size: u32) -> Result<()> {
         track_assert!(
             max_size <= self.size_hard_limit,
-            Failed,
+            ErrorKind::InvalidInput,
             "new_soft_limit={}, hard_limit={}",
             max_size,
             self.size_hard_limit
         );
         self.size_soft_limit = max_size;
         self.evict_exceeded_entries(0);
+        if (self.size_soft_limit as usize) < self.capacity() {
+            self.reallocate(max_size as usize);
+        }
         Ok(())
     }
 
-    pub(crate) fn push(&mut self, name: Vec<u8>, value: Vec<u8>) -> Option<HeaderField<'static>> {
-        let field = HeaderField::from_cow(Cow::Owned(name), Cow::Owned(value));
-        let entry_size = field.entry_size();
+    pub(crate) fn push(&mut self, name: &[u8], value: &[u8]) -> Option<HeaderField<'static>> {
+        let entry_size = (name.len() + value.len() + 32) as u32;
         if self.size_soft_limit < entry_size {
-            self.entries.clear();
-            Some(field)
+            self.clear();
+            let name = Octets::Owned(name.to_owned());
+            let value = Octets::Owned(value.to_owned());
+            Some(HeaderField::from_octets(name, value))
         } else {
             self.evict_exceeded_entries(entry_size);
+            self.reserve(name.len() + value.len());
+            let entry = EntryLocation {
+                offset: self.head as u32,
+                name_len: name.len() as u32,
+                value_len: value.len() as u32,
+            };
+            self.write(name);
+            self.write(value);
+            self.used += entry.len();
             self.size += entry_size;
-            self.entries.push_front(field);
+            self.entries.push_front(entry);
+            if let Some(ref mut index) = self.field_index {
+                index.insert(RingSlice(name, &[]), RingSlice(value, &[]), self.inserted);
+            }
+            self.inserted += 1;
             None
         }
     }
 
-    fn evict_exceeded_entries(&mut self, new_entry_size: u16) {
+    fn push_with_entry_name(&mut self, i: usize, value: &[u8]) -> Option<HeaderField<'static>> {
+        let mut buf = std::mem::take(&mut self.name_buffer);
+        let (name, _) = self.slices(&self.entries[i]);
+        buf.clear();
+        buf.extend_from_slice(name.0);
+        buf.extend_from_slice(name.1);
+        let evicted = self.push(&buf, value);
+        self.name_buffer = buf;
+        evicted
+    }
+
+    /// Starts recording the changes made to this table,
+    /// so that they can be undone by `rollback` method.
+    ///
+    /// The soft limit of the table size must not be changed until `commit` or `rollback` is called.
+    pub(crate) fn begin(&mut self) {
+        self.journal = Some(Journal {
+            inserted: self.inserted,
+            evicted: Vec::new(),
+        });
+        if let Some(ref mut index) = self.field_index {
+            index.begin();
+        }
+    }
+
+    /// Stops recording the changes made to this table, keeping them.
+    pub(crate) fn commit(&mut self) {
+        self.journal = None;
+        if let Some(ref mut index) = self.field_index {
+            index.commit();
+        }
+    }
+
+    /// Undoes the changes made to this table since `begin` was called.
+    ///
+    /// The entries inserted since then are removed,
+    /// and the evicted ones are written back to the ring buffer.
+    /// This does nothing if `begin` has not been called.
+    pub(crate) fn rollback(&mut self) {
+        let journal = if let Some(journal) = self.journal.take() {
+            journal
+        } else {
+            return;
+        };
+        let inserted = cmp::min(self.inserted - journal.inserted, self.entries.len() as u64);
+        for _ in 0..inserted {
+            let entry = self.entries.pop_front().expect("Never fails");
+            self.head = entry.offset as usize;
+            self.used -= entry.len();
+            self.size -= entry.entry_size();
+        }
+        for (mut entry, octets) in journal.evicted.into_iter().rev() {
+            let capacity = self.capacity();
+            let offset = wrap(self.head + capacity - self.used - octets.len(), capacity);
+            self.write_at(offset, &octets);
+            entry.offset = offset as u32;
+            self.used += entry.len();
+            self.size += entry.entry_size();
+            self.entries.push_back(entry);
+        }
+        self.inserted = journal.inserted;
+        if let Some(ref mut index) = self.field_index {
+            index.rollback();
+        }
+    }
+
+    fn find(&self, name: &[u8], value: &[u8]) -> (Option<usize>, Option<usize>) {
+        let matches = |i: usize, with_value: bool| {
+            let (entry_name, entry_value) = self.slices(&self.entries[i]);
+            entry_name.eq_bytes(name) && (!with_value || entry_value.eq_bytes(value))
+        };
+        if let Some(ref index) = self.field_index {
+            let (name, value) = (RingSlice(name, &[]), RingSlice(value, &[]));
+            let matched = index
+                .fields
+                .get(&index.hash_field(name, value))
+                .and_then(|&seq| self.position(seq))
+                .filter(|&i| matches(i, true));
+            if matched.is_some() {
+                return (matched, matched);
+            }
+            let name_matched = index
+                .names
+                .get(&index.hash_name(name))
+                .and_then(|&seq| self.position(seq))
+                .filter(|&i| matches(i, false));
+            (None, name_matched)
+        } else {
+            let mut name_matched = None;
+            for i in 0..self.entries.len() {
+                if !matches(i, false) {
+                    continue;
+                }
+                if matches(i, true) {
+                    return (Some(i), Some(i));
+                }
+                if name_matched.is_none() {
+                    name_matched = Some(i);
+                }
+            }
+            (None, name_matched)
+        }
+    }
+
+    fn position(&self, seq: u64) -> Option<usize> {
+        let i = (self.inserted - seq - 1) as usize;
+        if i < self.entries.len() {
+            Some(i)
+        } else {
+            None
+        }
+    }
+
+    fn clear(&mut self) {
+        if let Some(ref mut index) = self.field_index {
+            index.clear();
+        }
+        while let Some(evicted) = self.entries.pop_back() {
+            let seq = self.inserted - self.entries.len() as u64 - 1;
+            self.record_eviction(evicted, seq);
+        }
+        self.size = 0;
+        self.used = 0;
+        self.head = 0;
+    }
+
+    fn evict_exceeded_entries(&mut self, new_entry_size: u32) {
         while self.size_soft_limit - new_entry_size < self.size {
+            let seq = self.inserted - self.entries.len() as u64;
             let evicted = self.entries.pop_back().expect("Never fails");
+            if let Some(ref mut index) = self.field_index {
+                let (name, value) = entry_slices(&self.buffer, &evicted);
+                index.remove(name, value, seq);
+            }
             self.size -= evicted.entry_size();
+            self.used -= evicted.len();
+            self.record_eviction(evicted, seq);
+        }
+    }
+
+    fn record_eviction(&mut self, entry: EntryLocation, seq: u64) {
+        if let Some(ref mut journal) = self.journal {
+            if seq < journal.inserted {
+                let (name, value) = entry_slices(&self.buffer, &entry);
+                let mut octets = Vec::with_capacity(entry.len());
+                for part in &[name.0, name.1, value.0, value.1] {
+                    octets.extend_from_slice(part);
+                }
+                journal.evicted.push((entry, octets));
+            }
+        }
+    }
+
+    fn reserve(&mut self, additional: usize) {
+        let required = self.used + additional;
+        if self.capacity() < required {
+            let doubled = cmp::min(self.capacity() * 2, self.size_soft_limit as usize);
+            self.reallocate(cmp::max(required, doubled));
+        }
+    }
+
+    fn reallocate(&mut self, capacity: usize) {
+        debug_assert!(self.used <= capacity);
+        let mut buffer = vec![0; capacity];
+        let mut offset = 0;
+        for entry in self.entries.iter_mut().rev() {
+            let len = entry.len();
+            let (first, second) = ring_slices(&self.buffer, entry.offset as usize, len);
+            buffer[offset..][..first.len()].copy_from_slice(first);
+            buffer[offset + first.len()..][..second.len()].copy_from_slice(second);
+            entry.offset = offset as u32;
+            offset += len;
+        }
+        self.buffer = buffer.into_boxed_slice();
+        self.head = wrap(self.used, capacity);
+    }
+
+    fn write(&mut self, octets: &[u8]) {
+        self.write_at(self.head, octets);
+        self.head = wrap(self.head + octets.len(), self.capacity());
+    }
+
+    fn write_at(&mut self, offset: usize, octets: &[u8]) {
+        if octets.is_empty() {
+            return;
+        }
+        let buffer = &mut self.buffer;
+        let first = cmp::min(octets.len(), buffer.len() - offset);
+        buffer[offset..][..first].copy_from_slice(&octets[..first]);
+        buffer[..octets.len() - first].copy_from_slice(&octets[first..]);
+    }
+
+    fn slices(&self, entry: &EntryLocation) -> (RingSlice<'_>, RingSlice<'_>) {
+        entry_slices(&self.buffer, entry)
+    }
+
+    fn field(&self, entry: &EntryLocation) -> HeaderField<'static> {
+        let (name, value) = self.slices(entry);
+        let mut octets = Vec::with_capacity(entry.len());
+        for part in &[name.0, name.1, value.0, value.1] {
+            octets.extend_from_slice(part);
+        }
+        let octets: Arc<[u8]> = Arc::from(octets);
+        let name_len = entry.name_len as usize;
+        let name = Octets::Shared(Arc::clone(&octets), 0..name_len);
+        let value = Octets::Shared(octets, name_len..entry.len());
+        HeaderField::from_octets(name, value)
+    }
+}
+impl fmt::Debug for DynamicTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DynamicTable")
+            .field("entries", &DebugEntries(self))
+            .field("size", &self.size)
+            .field("size_soft_limit", &self.size_soft_limit)
+            .field("size_hard_limit", &self.size_hard_limit)
+            .finish()
+    }
+}
+
+/// An iterator over the entries of a dynamic table.
+///
+/// This is created by [`DynamicTable::entries`](./struct.DynamicTable.html#method.entries).
+#[derive(Debug)]
+pub struct Entries<'a> {
+    table: &'a DynamicTable,
+    entries: vec_deque::Iter<'a, EntryLocation>,
+}
+impl<'a> Iterator for Entries<'a> {
+    type Item = HeaderField<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.entries.next().map(|e| self.table.field(e))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.entries.size_hint()
+    }
+}
+impl<'a> ExactSizeIterator for Entries<'a> {}
+
+/// The location of an entry in the ring buffer of a dynamic table.
+///
+/// The name is immediately followed by the value.
+#[derive(Debug, Clone, Copy)]
+struct EntryLocation {
+    offset: u32,
+    name_len: u32,
+    value_len: u32,
+}
+impl EntryLocation {
+    fn len(&self) -> usize {
+        self.name_len as usize + self.value_len as usize
+    }
+
+    fn entry_size(&self) -> u32 {
+        self.name_len + self.value_len + 32
+    }
+}
+
+/// Changes made to a dynamic table since `DynamicTable::begin` was called.
+///
+/// Only the entries existing before the call are recorded as evicted (together with their octets),
+/// in order of eviction.
+#[derive(Debug, Clone)]
+struct Journal {
+    inserted: u64,
+    evicted: Vec<(EntryLocation, Vec<u8>)>,
+}
+
+/// Hash index over the entries of a dynamic table.
+///
+/// The entries are identified by their insertion sequence numbers,
+/// which are not affected by the later insertions (unlike HPACK indices).
+/// Only the newest entry is kept for each key,
+/// because the older entries are always evicted earlier than it.
+///
+/// Hash collisions are tolerated: the candidates are verified by the caller,
+/// and a collision only results in a missed match.
+///
+/// While `undo` is `Some`, the previous values of the updated keys are recorded in it
+/// (`true` of the first element denotes `names`, and `false` denotes `fields`).
+#[derive(Debug, Default, Clone)]
+struct FieldIndex {
+    hash_builder: RandomState,
+    names: HashMap<u64, u64>,
+    fields: HashMap<u64, u64>,
+    undo: Option<Vec<(bool, u64, Option<u64>)>>,
+}
+impl FieldIndex {
+    fn insert(&mut self, name: RingSlice, value: RingSlice, seq: u64) {
+        let name_hash = self.hash_name(name);
+        let field_hash = self.hash_field(name, value);
+        self.update(true, name_hash, Some(seq));
+        self.update(false, field_hash, Some(seq));
+    }
+
+    fn remove(&mut self, name: RingSlice, value: RingSlice, seq: u64) {
+        let name_hash = self.hash_name(name);
+        if self.names.get(&name_hash) == Some(&seq) {
+            self.update(true, name_hash, None);
+        }
+        let field_hash = self.hash_field(name, value);
+        if self.fields.get(&field_hash) == Some(&seq) {
+            self.update(false, field_hash, None);
+        }
+    }
+
+    fn clear(&mut self) {
+        if let Some(ref mut undo) = self.undo {
+            undo.extend(self.names.drain().map(|(k, v)| (true, k, Some(v))));
+            undo.extend(self.fields.drain().map(|(k, v)| (false, k, Some(v))));
+        }
+        self.names.clear();
+        self.fields.clear();
+    }
+
+    fn update(&mut self, is_name: bool, hash: u64, seq: Option<u64>) {
+        let map = if is_name {
+            &mut self.names
+        } else {
+            &mut self.fields
+        };
+        let old = match seq {
+            Some(seq) => map.insert(hash, seq),
+            None => map.remove(&hash),
+        };
+        if let Some(ref mut undo) = self.undo {
+            undo.push((is_name, hash, old));
         }
     }
+
+    fn begin(&mut self) {
+        self.undo = Some(Vec::new());
+    }
+
+    fn commit(&mut self) {
+        self.undo = None;
+    }
+
+    fn rollback(&mut self) {
+        for (is_name, hash, seq) in self.undo.take().into_iter().flatten().rev() {
+            let map = if is_name {
+                &mut self.names
+            } else {
+                &mut self.fields
+            };
+            match seq {
+                Some(seq) => map.insert(hash, seq),
+                None => map.remove(&hash),
+            };
+        }
+    }
+
+    fn hash_name(&self, name: RingSlice) -> u64 {
+        let mut hasher = self.hash_builder.build_hasher();
+        name.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    fn hash_field(&self, name: RingSlice, value: RingSlice) -> u64 {
+        let mut hasher = self.hash_builder.build_hasher();
+        name.hash(&mut hasher);
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// Octets which may wrap around the end of a ring buffer.
+#[derive(Clone, Copy)]
+struct RingSlice<'a>(&'a [u8], &'a [u8]);
+impl<'a> RingSlice<'a> {
+    fn eq_bytes(&self, bytes: &[u8]) -> bool {
+        let (first, second) = (self.0, self.1);
+        first.len() + second.len() == bytes.len()
+            && &bytes[..first.len()] == first
+            && &bytes[first.len()..] == second
+    }
+
+    fn hash<H: Hasher>(&self, hasher: &mut H) {
+        hasher.write_usize(self.0.len() + self.1.len());
+        hasher.write(self.0);
+        hasher.write(self.1);
+    }
+}
+
+impl<'a> fmt::Debug for RingSlice<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.0.iter().chain(self.1)).finish()
+    }
+}
+
+/// Formats the entries of a dynamic table directly from the ring buffer.
+struct DebugEntries<'a>(&'a DynamicTable);
+impl<'a> fmt::Debug for DebugEntries<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let table = self.0;
+        f.debug_list()
+            .entries(table.entries.iter().map(|e| DebugEntry(table.slices(e))))
+            .finish()
+    }
+}
+
+struct DebugEntry<'a>((RingSlice<'a>, RingSlice<'a>));
+impl<'a> fmt::Debug for DebugEntry<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (name, value) = self.0;
+        f.debug_struct("HeaderField")
+            .field("name", &name)
+            .field("value", &value)
+            .finish()
+    }
+}
+
+fn entry_slices<'a>(buffer: &'a [u8], entry: &EntryLocation) -> (RingSlice<'a>, RingSlice<'a>) {
+    let offset = entry.offset as usize;
+    let name_len = entry.name_len as usize;
+    let value_offset = wrap(offset + name_len, buffer.len());
+    let (name0, name1) = ring_slices(buffer, offset, name_len);
+    let (value0, value1) = ring_slices(buffer, value_offset, entry.value_len as usize);
+    (RingSlice(name0, name1), RingSlice(value0, value1))
+}
+
+fn ring_slices(buffer: &[u8], offset: usize, len: usize) -> (&[u8], &[u8]) {
+    let first = cmp::min(len, buffer.len() - offset);
+    (&buffer[offset..][..first], &buffer[..len - first])
+}
+
+fn wrap(position: usize, capacity: usize) -> usize {
+    if capacity == 0 {
+        0
+    } else {
+        position % capacity
+    }
 }
 
 /// Entry Index.
 #[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
-pub struct Index(u16);
+pub struct Index(u32);
 impl Index {
     /// Makes a new `Index` instance.
     ///
@@ -185,8 +760,8 @@ impl Index {
     /// assert!(Index::new(1).is_ok());
     /// assert!(Index::new(0).is_err());
     /// ```
-    pub fn new(index: u16) -> Result<Self> {
-        track_assert_ne!(index, 0, Failed);
+    pub fn new(index: u32) -> Result<Self> {
+        track_assert_ne!(index, 0, ErrorKind::InvalidIndex);
         Ok(Index(index))
     }
 
@@ -199,26 +774,26 @@ impl Index {
     /// ```
     /// use hpack_codec::table::Index;
     ///
-    /// assert_eq!(Index::dynamic_table_offset().as_u16(), 62);
+    /// assert_eq!(Index::dynamic_table_offset().as_u32(), 62);
     /// assert_eq!(Index::dynamic_table_offset() + 8, Index::new(70).unwrap());
     /// ```
     pub fn dynamic_table_offset() -> Self {
-        Index(StaticEntry::entries_count() as u16 + 1)
+        Index(StaticEntry::entries_count() as u32 + 1)
     }
 
     /// Returns the value of this index.
-    pub fn as_u16(&self) -> u16 {
+    pub fn as_u32(&self) -> u32 {
         self.0
     }
 }
-impl Add<u16> for Index {
+impl Add<u32> for Index {
     type Output = Self;
-    fn add(self, rhs: u16) -> Self::Output {
+    fn add(self, rhs: u32) -> Self::Output {
         Index(self.0.checked_add(rhs).expect("Overflow"))
     }
 }
-impl AddAssign<u16> for Index {
-    fn add_assign(&mut self, rhs: u16) {
+impl AddAssign<u32> for Index {
+    fn add_assign(&mut self, rhs: u32) {
         self.0 = self.0.checked_add(rhs).expect("Overflow");
     }
 }
@@ -296,15 +871,21 @@ impl From<StaticEntry> for Index {
 }
 impl From<StaticEntry> for HeaderField<'static> {
     fn from(f: StaticEntry) -> Self {
+        let (name, value) = f.name_and_value();
+        HeaderField::from_static(name, value)
+    }
+}
+impl StaticEntry {
+    fn name_and_value(self) -> (&'static [u8], &'static [u8]) {
         macro_rules! field {
             ($name:expr, $value: expr) => {
-                HeaderField::from_cow(Cow::Borrowed($name), Cow::Borrowed($value))
+                ($name, $value)
             };
             ($name:expr) => {
-                HeaderField::from_cow(Cow::Borrowed($name), Cow::Borrowed(b""))
+                ($name, b"")
             };
         }
-        match f {
+        match self {
             StaticEntry::Authority => field!(b":authority"),
             StaticEntry::Method => field!(b":method", b"GET"),
             StaticEntry::MethodGet => field!(b":method", b"GET"),
@@ -378,7 +959,7 @@ impl From<StaticEntry> for HeaderField<'static> {
 /// A list specifying the entries of the static table.
 ///
 /// See: [Appendix A.  Static Table Definition)(https://tools.ietf.org/html/rfc7541#appendix-A)
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 #[allow(missing_docs)]
 pub enum StaticEntry {
     Authority,
@@ -472,7 +1053,7 @@ impl StaticEntry {
     /// If the value of `index` is greater than `StaticEntry::entries_count()`,
     /// this function will return `None`.
     pub fn from_index(index: Index) -> Option<Self> {
-        Some(match index.as_u16() {
+        Some(match index.as_u32() {
             1 => StaticEntry::Authority,
             2 => StaticEntry::MethodGet,
             3 => StaticEntry::MethodPost,
@@ -537,4 +1118,318 @@ impl StaticEntry {
             _ => return None,
         })
     }
+
+    /// Returns the entry which exactly matches the given field.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hpack_codec::table::{Index, StaticEntry};
+    ///
+    /// let entry = StaticEntry::find(b":status", b"404").unwrap();
+    /// assert_eq!(Index::from(entry).as_u32(), 13);
+    ///
+    /// assert!(StaticEntry::find(b":status", b"403").is_none());
+    /// assert!(StaticEntry::find(b"content-type", b"text/plain").is_none());
+    /// ```
+    pub fn find(name: &[u8], value: &[u8]) -> Option<Self> {
+        let entry = match (Self::find_name(name)?, value) {
+            (StaticEntry::MethodGet, b"POST") => StaticEntry::MethodPost,
+            (StaticEntry::PathRoot, b"/index.html") => StaticEntry::PathIndexHtml,
+            (StaticEntry::SchemeHttp, b"https") => StaticEntry::SchemeHttps,
+            (StaticEntry::Status200, b"204") => StaticEntry::Status204,
+            (StaticEntry::Status200, b"206") => StaticEntry::Status206,
+            (StaticEntry::Status200, b"304") => StaticEntry::Status304,
+            (StaticEntry::Status200, b"400") => StaticEntry::Status400,
+            (StaticEntry::Status200, b"404") => StaticEntry::Status404,
+            (StaticEntry::Status200, b"500") => StaticEntry::Status500,
+            (entry, _) => entry,
+        };
+        if entry.name_and_value().1 == value {
+            Some(entry)
+        } else {
+            None
+        }
+    }
+
+    /// Returns the first entry which has the given name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hpack_codec::table::{Index, StaticEntry};
+    ///
+    /// let entry = StaticEntry::find_name(b"content-type").unwrap();
+    /// assert_eq!(Index::from(entry).as_u32(), 31);
+    ///
+    /// let entry = StaticEntry::find_name(b":status").unwrap();
+    /// assert_eq!(Index::from(entry).as_u32(), 8);
+    ///
+    /// assert!(StaticEntry::find_name(b"x-custom").is_none());
+    /// ```
+    pub fn find_name(name: &[u8]) -> Option<Self> {
+        Some(match name {
+            b":authority" => StaticEntry::Authority,
+            b":method" => StaticEntry::MethodGet,
+            b":path" => StaticEntry::PathRoot,
+            b":scheme" => StaticEntry::SchemeHttp,
+            b":status" => StaticEntry::Status200,
+            b"accept-charset" => StaticEntry::AcceptCharset,
+            b"accept-encoding" => StaticEntry::AcceptEncodingGzipDeflate,
+            b"accept-language" => StaticEntry::AcceptLanguage,
+            b"accept-ranges" => StaticEntry::AcceptRanges,
+            b"accept" => StaticEntry::Accept,
+            b"access-control-allow-origin" => StaticEntry::AccessControlAllowOrigin,
+            b"age" => StaticEntry::Age,
+            b"allow" => StaticEntry::Allow,
+            b"authorization" => StaticEntry::Authorization,
+            b"cache-control" => StaticEntry::CacheControl,
+            b"content-disposition" => StaticEntry::ContentDisposition,
+            b"content-encoding" => StaticEntry::ContentEncoding,
+            b"content-language" => StaticEntry::ContentLanguage,
+            b"content-length" => StaticEntry::ContentLength,
+            b"content-location" => StaticEntry::ContentLocation,
+            b"content-range" => StaticEntry::ContentRange,
+            b"content-type" => StaticEntry::ContentType,
+            b"cookie" => StaticEntry::Cookie,
+            b"date" => StaticEntry::Date,
+            b"etag" => StaticEntry::Etag,
+            b"expect" => StaticEntry::Expect,
+            b"expires" => StaticEntry::Expires,
+            b"from" => StaticEntry::From,
+            b"host" => StaticEntry::Host,
+            b"if-match" => StaticEntry::IfMatch,
+            b"if-modified-since" => StaticEntry::IfModifiedSince,
+            b"if-none-match" => StaticEntry::IfNoneMatch,
+            b"if-range" => StaticEntry::IfRange,
+            b"if-unmodified-since" => StaticEntry::IfUnmodifiedSince,
+            b"last-modified" => StaticEntry::LastModified,
+            b"link" => StaticEntry::Link,
+            b"location" => StaticEntry::Location,
+            b"max-forwards" => StaticEntry::MaxForwards,
+            b"proxy-authenticate" => StaticEntry::ProxyAuthenticate,
+            b"proxy-authorization" => StaticEntry::ProxyAuthorization,
+            b"range" => StaticEntry::Range,
+            b"referer" => StaticEntry::Referer,
+            b"refresh" => StaticEntry::Refresh,
+            b"retry-after" => StaticEntry::RetryAfter,
+            b"server" => StaticEntry::Server,
+            b"set-cookie" => StaticEntry::SetCookie,
+            b"strict-transport-security" => StaticEntry::StrictTransportSecurity,
+            b"transfer-encoding" => StaticEntry::TransferEncoding,
+            b"user-agent" => StaticEntry::UserAgent,
+            b"vary" => StaticEntry::Vary,
+            b"via" => StaticEntry::Via,
+            b"www-authenticate" => StaticEntry::WwwAuthenticate,
+            _ => return None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ring_buffer() {
+        let mut table = Table::new(100);
+        assert_eq!(table.dynamic().capacity(), 0);
+
+        // [32 + 3 + 17] + [32 + 3 + 9]
+        assert!(table
+            .dynamic_mut()
+            .push(b"foo", b"aaaaaaaaaaaaaaaaa")
+            .is_none());
+        assert!(table.dynamic_mut().push(b"bar", b"bbbbbbbbb").is_none());
+        assert_eq!(table.dynamic().len(), 2);
+        assert_eq!(table.dynamic().capacity(), 40);
+
+        // Evicts the oldest entry and wraps around the end of the buffer
+        assert!(table
+            .dynamic_mut()
+            .push(b"baz", b"ccccccccccccccc")
+            .is_none());
+        assert_eq!(table.dynamic().len(), 2);
+        assert_eq!(table.dynamic().size(), 94);
+        assert_eq!(table.dynamic().capacity(), 40);
+        let fields = table
+            .dynamic()
+            .entries()
+            .map(HeaderField::into_owned)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            fields[0],
+            HeaderField::new(b"baz", b"ccccccccccccccc").unwrap()
+        );
+        assert_eq!(fields[1], HeaderField::new(b"bar", b"bbbbbbbbb").unwrap());
+
+        // The referenced name survives the eviction of its entry
+        let index = Index::dynamic_table_offset() + 1;
+        let evicted =
+            track_try_unwrap!(table.push_with_indexed_name(index, b"ddddddddddddddddddddddd"));
+        assert!(evicted.is_none());
+        assert_eq!(table.dynamic().len(), 1);
+        assert_eq!(
+            track_try_unwrap!(table.get(Index::dynamic_table_offset())),
+            HeaderField::new(b"bar", b"ddddddddddddddddddddddd").unwrap()
+        );
+        assert_eq!(
+            table.find(b"bar", b"ddddddddddddddddddddddd"),
+            Some((Index::dynamic_table_offset(), true))
+        );
+
+        // The fields derived from the table are not affected by later insertions
+        assert_eq!(fields[1].value(), b"bbbbbbbbb");
+
+        track_try_unwrap!(table.dynamic_mut().set_size_soft_limit(30));
+        assert!(table.dynamic().is_empty());
+        assert_eq!(table.dynamic().capacity(), 30);
+    }
+
+    #[test]
+    fn static_entry_lookup() {
+        for i in 1..=StaticEntry::entries_count() as u32 {
+            let entry = StaticEntry::from_index(Index(i)).unwrap();
+            let (name, value) = entry.name_and_value();
+            assert_eq!(StaticEntry::find(name, value), Some(entry));
+
+            let first = StaticEntry::find_name(name).unwrap();
+            assert_eq!(first.name_and_value().0, name);
+            assert!(Index::from(first) <= Index(i));
+            if i > 1 {
+                let previous = StaticEntry::from_index(Index(i - 1)).unwrap();
+                if previous.name_and_value().0 != name {
+                    assert_eq!(first, entry);
+                }
+            }
+        }
+        assert_eq!(StaticEntry::find(b":method", b"PUT"), None);
+        assert_eq!(StaticEntry::find(b"accept", b"*/*"), None);
+        assert_eq!(StaticEntry::find(b"Accept", b""), None);
+        assert_eq!(StaticEntry::find_name(b""), None);
+    }
+
+    #[test]
+    fn field_index() {
+        let names: [&[u8]; 4] = [b"foo", b"bar", b"content-type", b":path"];
+        let values: [&[u8]; 4] = [b"", b"a", b"text/plain", b"/index.html"];
+
+        let mut indexed = Table::with_field_index(200);
+        let mut scanned = Table::new(200);
+        for i in 0..100 {
+            let name = names[i % names.len()];
+            let value = values[(i * 7 / 3) % values.len()];
+            indexed.dynamic_mut().push(name, value);
+            scanned.dynamic_mut().push(name, value);
+            if i == 50 {
+                track_try_unwrap!(indexed.dynamic_mut().set_size_soft_limit(120));
+                track_try_unwrap!(scanned.dynamic_mut().set_size_soft_limit(120));
+            }
+
+            for name in names.iter().chain(&[&b"baz"[..]]) {
+                for value in values.iter().chain(&[&b"qux"[..]]) {
+                    let found = indexed.find(name, value);
+                    assert_eq!(found, scanned.find(name, value));
+                    if let Some((index, value_matched)) = found {
+                        let entry = track_try_unwrap!(indexed.get(index));
+                        assert_eq!(entry.name(), *name);
+                        assert_eq!(entry.value() == *value, value_matched);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn insertion_does_not_copy_buffer() {
+        use crate::field::LiteralHeaderField;
+        use crate::{Decoder, Encoder};
+
+        let big_value = [b'a'; 150];
+        let values = (0..6).map(|i| vec![b'0' + i; 20]).collect::<Vec<_>>();
+        let names = (0..6).map(|i| format!("x-{}", i)).collect::<Vec<_>>();
+
+        let mut encoder = Encoder::new(256);
+        let mut block = track_try_unwrap!(encoder.enter_header_block(Vec::new()));
+        track_try_unwrap!(
+            block.encode_field(LiteralHeaderField::new(b"big", &big_value).with_indexing())
+        );
+        for (name, value) in names.iter().zip(&values) {
+            track_try_unwrap!(
+                block.encode_field(LiteralHeaderField::new(name.as_bytes(), value).with_indexing())
+            );
+        }
+        let block = block.finish();
+
+        let mut decoder = Decoder::new(256);
+        let mut fields = Vec::new();
+        let mut buffer = None;
+        {
+            let mut block = track_try_unwrap!(decoder.enter_header_block(&block[..]));
+            while let Some(field) = track_try_unwrap!(block.decode_field()) {
+                fields.push(field.into_owned());
+                let dynamic = block.table().dynamic();
+                if fields.len() == 2 {
+                    assert_eq!(dynamic.capacity(), 256);
+                    buffer = Some(dynamic.buffer.as_ptr());
+                } else if fields.len() > 2 {
+                    assert_eq!(Some(dynamic.buffer.as_ptr()), buffer);
+                }
+            }
+        }
+        assert_eq!(fields.len(), 7);
+        assert_eq!(decoder.table().dynamic().len(), 4);
+        assert_eq!(fields[0].name(), b"big");
+        assert_eq!(fields[0].value(), &big_value[..]);
+        for (field, (name, value)) in fields[1..].iter().zip(names.iter().zip(&values)) {
+            assert_eq!(field.name(), name.as_bytes());
+            assert_eq!(field.value(), &value[..]);
+        }
+    }
+
+    #[test]
+    fn rollback() {
+        let names: [&[u8]; 4] = [b"foo", b"bar", b"content-type", b":path"];
+        let values: [&[u8]; 4] = [b"", b"a", b"text/plain", b"/index.html"];
+        let assert_same = |x: &Table, y: &Table| {
+            let x_entries = x.dynamic().entries().collect::<Vec<_>>();
+            assert_eq!(x_entries, y.dynamic().entries().collect::<Vec<_>>());
+            assert_eq!(x.dynamic().size(), y.dynamic().size());
+            for name in names.iter().chain(&[&b"baz"[..]]) {
+                for value in values.iter() {
+                    assert_eq!(x.find(name, value), y.find(name, value));
+                }
+            }
+        };
+
+        let mut table = Table::with_field_index(150);
+        let mut i = 0;
+        for round in 0..30 {
+            let snapshot = table.clone();
+            table.dynamic_mut().begin();
+            for _ in 0..round % 5 {
+                table
+                    .dynamic_mut()
+                    .push(names[i % names.len()], values[(i * 7 / 3) % values.len()]);
+                i += 1;
+            }
+            if round % 7 == 3 {
+                // Too large to be inserted (clears the table)
+                table.dynamic_mut().push(b"baz", &[b'a'; 150]);
+            }
+            if round % 3 == 0 {
+                table.dynamic_mut().commit();
+            } else {
+                table.dynamic_mut().rollback();
+                assert_same(&table, &snapshot);
+            }
+
+            // The index is kept consistent with the entries
+            let mut scanned = Table::new(150);
+            for field in table.dynamic().entries().collect::<Vec<_>>().iter().rev() {
+                scanned.dynamic_mut().push(field.name(), field.value());
+            }
+            assert_same(&table, &scanned);
+        }
+    }
 }
@@ -0,0 +1,214 @@
+use crate::field::{FieldRepresentation, HeaderField, LiteralFieldForm};
+use crate::{Decoder, Encoder, Result};
+use std::io::Write;
+
+/// Header Block Transcoder.
+///
+/// This decodes header blocks in the context of an inbound connection
+/// and re-encodes them in the context of an outbound connection.
+/// It is intended for intermediaries such as HTTP/2 proxies.
+///
+/// The fields received as never indexed literals are always forwarded as never indexed literals
+/// (see: [6.2.3.  Literal Header Field Never Indexed]
+///  (https://tools.ietf.org/html/rfc7541#section-6.2.3)).
+/// The representations of the other fields are decided by the indexing policy of the outbound encoder.
+///
+/// # Examples
+///
+/// ```
+/// use hpack_codec::{Decoder, Encoder, Transcoder};
+/// use hpack_codec::field::{HeaderField, LiteralHeaderField};
+///
+/// let mut client = Encoder::new(4096);
+/// let mut header = client.enter_header_block(Vec::new()).unwrap();
+/// header.encode_header(b":method", b"GET").unwrap();
+/// header.encode_field(LiteralHeaderField::new(b"x-secret", b"foo").never_indexed()).unwrap();
+/// let inbound = header.finish();
+///
+/// let mut proxy = Transcoder::new(Decoder::new(4096), Encoder::new(4096));
+/// let outbound = proxy.transcode(&inbound[..], Vec::new()).unwrap();
+/// assert_eq!(outbound, inbound);
+///
+/// let mut server = Decoder::new(4096);
+/// let fields = server.enter_header_block(&outbound[..]).unwrap().decode_all().unwrap();
+/// assert_eq!(fields, [
+///     HeaderField::new(b":method", b"GET").unwrap(),
+///     HeaderField::new(b"x-secret", b"foo").unwrap(),
+/// ]);
+/// ```
+#[derive(Debug)]
+pub struct Transcoder {
+    decoder: Decoder,
+    encoder: Encoder,
+}
+impl Transcoder {
+    /// Makes a new `Transcoder` instance.
+    ///
+    /// `decoder` and `encoder` are used for the inbound and outbound connections respectively.
+    pub fn new(decoder: Decoder, encoder: Encoder) -> Self {
+        Transcoder { decoder, encoder }
+    }
+
+    /// Returns the reference to the decoder for the inbound connection.
+    pub fn decoder(&self) -> &Decoder {
+        &self.decoder
+    }
+
+    /// Returns the mutable reference to the decoder for the inbound connection.
+    pub fn decoder_mut(&mut self) -> &mut Decoder {
+        &mut self.decoder
+    }
+
+    /// Returns the reference to the encoder for the outbound connection.
+    pub fn encoder(&self) -> &Encoder {
+        &self.encoder
+    }
+
+    /// Returns the mutable reference to the encoder for the outbound connection.
+    pub fn encoder_mut(&mut self) -> &mut Encoder {
+        &mut self.encoder
+    }
+
+    /// Converts into the decoder and the encoder.
+    pub fn into_inner(self) -> (Decoder, Encoder) {
+        (self.decoder, self.encoder)
+    }
+
+    /// Transcodes the header block `block` and writes the resulting block to `writer`.
+    ///
+    /// # Errors
+    ///
+    /// If `block` cannot be decoded or the resulting block cannot be written,
+    /// an error will be returned.
+    /// In that case, the state of the outbound encoder is left unchanged
+    /// (see the documentation of `HeaderBlockEncoder`).
+    pub fn transcode<W: Write>(&mut self, block: &[u8], writer: W) -> Result<W> {
+        track!(self.transcode_with(block, writer, |field| Some(field)))
+    }
+
+    /// Transcodes the header block `block` as `transcode` method,
+    /// passing each decoded field to `f`.
+    ///
+    /// The field returned by `f` is encoded instead of the original one.
+    /// If `f` returns `None`, the field is removed from the resulting block.
+    ///
+    /// Note that the never indexed flag of a field is kept even if the field is rewritten by `f`.
+    ///
+    /// # Errors
+    ///
+    /// See the documentation of `transcode` method.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use hpack_codec::{Decoder, Encoder, Transcoder};
+    /// use hpack_codec::field::HeaderField;
+    ///
+    /// let mut client = Encoder::new(4096);
+    /// let mut header = client.enter_header_block(Vec::new()).unwrap();
+    /// header.encode_header(b"connection", b"keep-alive").unwrap();
+    /// header.encode_header(b"via", b"1.1 foo").unwrap();
+    /// let inbound = header.finish();
+    ///
+    /// let mut proxy = Transcoder::new(Decoder::new(4096), Encoder::new(4096));
+    /// let outbound = proxy
+    ///     .transcode_with(&inbound[..], Vec::new(), |field| match field.name() {
+    ///         b"connection" => None,
+    ///         b"via" => {
+    ///             let mut via = field.value().to_owned();
+    ///             via.extend_from_slice(b", 1.1 bar");
+    ///             Some(HeaderField::new(b"via", &via).unwrap().into_owned())
+    ///         }
+    ///         _ => Some(field),
+    ///     })
+    ///     .unwrap();
+    ///
+    /// let mut server = Decoder::new(4096);
+    /// let fields = server.enter_header_block(&outbound[..]).unwrap().decode_all().unwrap();
+    /// assert_eq!(fields, [HeaderField::new(b"via", b"1.1 foo, 1.1 bar").unwrap()]);
+    /// ```
+    pub fn transcode_with<W, F>(&mut self, block: &[u8], writer: W, mut f: F) -> Result<W>
+    where
+        W: Write,
+        F: for<'c> FnMut(HeaderField<'c>) -> Option<HeaderField<'c>>,
+    {
+        let mut input = track!(self.decoder.enter_header_block(block))?;
+        let mut output = track!(self.encoder.enter_header_block(writer))?;
+        while let Some((field, info)) = track!(input.decode_field_with_info())? {
+            let never_indexed = info.representation()
+                == FieldRepresentation::Literal(LiteralFieldForm::NeverIndexed);
+            let field = if let Some(field) = f(field) {
+                field
+            } else {
+                continue;
+            };
+            if never_indexed {
+                track!(output.encode_header_with_form(
+                    field.name(),
+                    field.value(),
+                    LiteralFieldForm::NeverIndexed
+                ))?;
+            } else {
+                track!(output.encode_header(field.name(), field.value()))?;
+            }
+        }
+        Ok(output.finish())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::field::LiteralHeaderField;
+    use crate::policy::NeverIndexing;
+    use crate::table::StaticEntry;
+
+    #[test]
+    fn never_indexed_fields_are_preserved() {
+        let mut client = Encoder::new(4096);
+        let mut header = track_try_unwrap!(client.enter_header_block(Vec::new()));
+        track_try_unwrap!(header.encode_header(b"foo", b"bar"));
+        track_try_unwrap!(header.encode_field(
+            LiteralHeaderField::with_indexed_name(StaticEntry::Cookie, b"a=b").never_indexed()
+        ));
+        track_try_unwrap!(header.encode_header(b"foo", b"bar"));
+        let inbound = header.finish();
+
+        let mut encoder = Encoder::new(4096);
+        encoder.set_indexing_policy(NeverIndexing);
+        let mut proxy = Transcoder::new(Decoder::new(4096), encoder);
+        let outbound = track_try_unwrap!(proxy.transcode_with(&inbound[..], Vec::new(), |f| {
+            if f.name() == b"cookie" {
+                Some(track_try_unwrap!(HeaderField::new(b"cookie", b"c=d")).into_owned())
+            } else {
+                Some(f)
+            }
+        }));
+        assert_eq!(proxy.decoder().table().dynamic().len(), 1);
+        assert!(proxy.encoder().table().dynamic().is_empty());
+
+        let mut server = Decoder::new(4096);
+        let mut block = track_try_unwrap!(server.enter_header_block(&outbound[..]));
+        let mut forms = Vec::new();
+        while let Some((field, info)) = track_try_unwrap!(block.decode_field_with_info()) {
+            forms.push((field.into_owned(), info.representation()));
+        }
+        assert_eq!(
+            forms,
+            [
+                (
+                    track_try_unwrap!(HeaderField::new(b"foo", b"bar")).into_owned(),
+                    FieldRepresentation::Literal(LiteralFieldForm::WithoutIndexing)
+                ),
+                (
+                    track_try_unwrap!(HeaderField::new(b"cookie", b"c=d")).into_owned(),
+                    FieldRepresentation::Literal(LiteralFieldForm::NeverIndexed)
+                ),
+                (
+                    track_try_unwrap!(HeaderField::new(b"foo", b"bar")).into_owned(),
+                    FieldRepresentation::Literal(LiteralFieldForm::WithoutIndexing)
+                ),
+            ]
+        );
+    }
+}